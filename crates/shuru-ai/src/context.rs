@@ -62,7 +62,7 @@ impl fmt::Display for Context {
                     result.push_str(&format!(
                         "    - {}:\n        command: {}\n        description: \"{}\"\n",
                         task_name,
-                        task_config.command,
+                        task_config.command_summary(),
                         task_config.description.as_deref().unwrap_or("")
                     ));
                 }
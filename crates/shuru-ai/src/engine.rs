@@ -13,15 +13,22 @@ use shuru_ai::{
     progress_tracker::ProgressTracker,
 };
 
-use shuru_tools::task_runner::TaskRunner;
+use shuru_tools::task_runner::{
+    process::{wait_forwarding_signals, Foreground},
+    TaskArgs, TaskRunner,
+};
 
 use std::{
     collections::HashMap,
     path::{Path, PathBuf},
     process::Stdio,
+    time::Duration,
 };
 use tokio;
 
+/// How long a command gets to exit after a forwarded signal before it is killed.
+const COMMAND_TERMINATE_GRACE: Duration = Duration::from_secs(5);
+
 pub type Result<T> = std::result::Result<T, EngineError>;
 
 pub struct ActionEngine {
@@ -234,7 +241,10 @@ impl ActionEngine {
     }
 
     pub async fn run_command(&self, command: &str, args: &[String]) -> Result<()> {
-        let shell = shuru_tools::task_runner::shell::Shell::from_env();
+        let shell = match self.context.config.as_ref().and_then(|config| config.shell) {
+            Some(name) => shuru_tools::task_runner::shell::Shell::from(name),
+            None => shuru_tools::task_runner::shell::Shell::from_env(),
+        };
         let mut async_command = shell.create_async_command();
         let escaped_args = args
             .iter()
@@ -253,19 +263,24 @@ impl ActionEngine {
             .current_dir(&self.context.work_dir)
             .arg(full_command);
         let shell_command = match &self.context.config {
-            Some(config) => match config.build_env_path() {
-                Ok(path) => shell_command.env("PATH", path),
-                Err(e) => {
-                    return Err(EngineError::ContextError(ContextError::Environment(
-                        e.to_string(),
-                    )))
-                }
-            },
+            Some(config) => {
+                let (path, env) = config
+                    .build_env_path()
+                    .and_then(|path| Ok((path, config.project_env(&self.context.work_dir)?)))
+                    .map_err(|e| {
+                        EngineError::ContextError(ContextError::Environment(e.to_string()))
+                    })?;
+                shell_command.env("PATH", path).envs(env)
+            }
             None => shell_command,
         };
         let mut child = shell_command.spawn().map_err(EngineError::Io)?;
 
-        let status = child.wait().await.map_err(EngineError::Io)?;
+        let foreground = Foreground::give(child.id());
+        let status = wait_forwarding_signals(&mut child, COMMAND_TERMINATE_GRACE)
+            .await
+            .map_err(EngineError::Io)?;
+        drop(foreground);
 
         if !status.success() {
             return Err(EngineError::CommandExecution(format!(
@@ -287,7 +302,7 @@ impl ActionEngine {
 
         let task_runner = TaskRunner::new(config);
 
-        if let Err(e) = task_runner.run_task(task) {
+        if let Err(e) = task_runner.run_task(task, &TaskArgs::default()) {
             return Err(EngineError::CommandExecution(format!(
                 "Task execution failed: {}",
                 e,
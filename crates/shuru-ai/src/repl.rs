@@ -3,8 +3,8 @@ use dialoguer::{theme::ColorfulTheme, Input, Select};
 use spinners::{Spinner, Spinners};
 
 use shuru_ai::{
+    client::{client_factory::AIClientFactory, AIClient},
     context::Context,
-    client::{AIClient, client_factory::AIClientFactory},
     engine::ActionEngine,
     plan::{AIPlan, Action},
 };
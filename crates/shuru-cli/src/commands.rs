@@ -1,3 +1,5 @@
+use std::io::IsTerminal;
+
 use clap::ValueEnum;
 use shuru_core::{config::Config, error::Error};
 use shuru_tools::version_manager::VersionManagerResolver;
@@ -28,15 +30,62 @@ pub fn update_versions(config: &Config) -> Result<std::process::ExitStatus, Erro
     std::process::exit(0);
 }
 
+/// Prints the public tasks as a tree grouped by their `group:` namespaces. When
+/// stdout is not a terminal, e.g. for shell completions, every task name and
+/// alias is printed on a line of its own instead.
 pub fn list_commands(config: Option<Config>) -> Result<std::process::ExitStatus, Error> {
     if let Some(config) = config {
-        for task_name in config.tasks.keys() {
-            println!("{}", task_name);
+        let task_names = config.public_task_names();
+
+        if std::io::stdout().is_terminal() {
+            print_task_tree(&config, &task_names);
+        } else {
+            for task_name in task_names {
+                println!("{}", task_name);
+                for alias in &config.tasks[task_name].aliases {
+                    println!("{}", alias);
+                }
+            }
         }
     }
     std::process::exit(0);
 }
 
+pub fn list_group(config: &Config, group: &str) -> Result<std::process::ExitStatus, Error> {
+    print_task_tree(config, &config.task_group(group));
+    std::process::exit(0);
+}
+
+fn print_task_tree(config: &Config, task_names: &[&str]) {
+    let mut previous_groups: Vec<&str> = Vec::new();
+
+    for task_name in task_names {
+        let segments: Vec<&str> = task_name.split(':').collect();
+        let (groups, leaf) = segments.split_at(segments.len() - 1);
+
+        let common = previous_groups
+            .iter()
+            .zip(groups)
+            .take_while(|(previous, group)| previous == group)
+            .count();
+        for (depth, group) in groups.iter().enumerate().skip(common) {
+            println!("{}{}:", "  ".repeat(depth), group);
+        }
+
+        let task = &config.tasks[*task_name];
+        let mut line = format!("{}{}", "  ".repeat(groups.len()), leaf[0]);
+        if !task.aliases.is_empty() {
+            line.push_str(&format!(" ({})", task.aliases.join(", ")));
+        }
+        if let Some(description) = &task.description {
+            line.push_str(&format!("  \x1b[90m{}\x1b[0m", description));
+        }
+        println!("{}", line);
+
+        previous_groups = groups.to_vec();
+    }
+}
+
 pub fn clear_cache() -> Result<std::process::ExitStatus, Error> {
     let home_dir = dirs::home_dir().ok_or_else(|| Error::HomeDirectoryNotFound)?;
     let cache_dir = home_dir.join(".shuru");
@@ -3,8 +3,12 @@ _shuru() {
     COMPREPLY=()
     cur="${COMP_WORDS[COMP_CWORD]}"
     prev="${COMP_WORDS[COMP_CWORD-1]}"
+    # Keep `group:task` names in one word; COMP_WORDBREAKS splits at colons.
+    if declare -F _get_comp_words_by_ref >/dev/null; then
+        _get_comp_words_by_ref -n : cur prev
+    fi
 
-    local options="-h --help -V --version --completions --list-commands --update-versions --clear-cache"
+    local options="-h --help -V --version --completions --list-commands --update-versions --clear-cache -j --jobs --force --watch -p --parallel --keep-going --output --timestamps --start --stop --status --logs -f --follow"
 
     if [[ "$prev" == -* ]]; then
         COMPREPLY=( $( compgen -W "$options" -- "$cur" ) )
@@ -14,6 +18,9 @@ _shuru() {
     local tasks
     tasks=$(shuru --list-commands)
     COMPREPLY=( $( compgen -W "$tasks" -- "$cur" ) )
+    if declare -F __ltrim_colon_completions >/dev/null; then
+        __ltrim_colon_completions "$cur"
+    fi
 }
 
 complete -F _shuru shuru
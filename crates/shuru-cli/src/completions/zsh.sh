@@ -17,12 +17,30 @@ _shuru() {
         '--version[Print version]'
         '--update-versions[Update all commands to versions in shuru.toml]'
         '--clear-cache[Clear all cached versions]'
+        '-j[Number of tasks to run in parallel]: :'
+        '--jobs[Number of tasks to run in parallel]: :'
+        '--force[Run tasks even if their sources are unchanged]'
+        '--watch[Re-run the task when its files change]'
+        '-p[Run all given tasks at the same time]'
+        '--parallel[Run all given tasks at the same time]'
+        '--keep-going[Keep running other tasks after one of them fails]'
+        '--output[How task output is shown]:mode:(interleaved prefixed grouped)'
+        '--timestamps[Add a timestamp to prefixed or grouped output]'
+        '--start[Start a service task in the background]:task:($commands)'
+        '--stop[Stop a service started with --start]:task:($commands)'
+        '--status[Show which service tasks are running]'
+        '--logs[Print the logs of a service started with --start]:task:($commands)'
+        '-f[Keep printing new lines with --logs]'
+        '--follow[Keep printing new lines with --logs]'
     )
 
     if [[ $words[2] == -* ]]; then
         _arguments -s $options
     else
-        _describe -t commands 'shuru commands' commands "$@"
+        # Colons separate a name from its description in _describe.
+        local -a described
+        described=("${commands[@]//:/\\:}")
+        _describe -t commands 'shuru commands' described "$@"
     fi
 }
 
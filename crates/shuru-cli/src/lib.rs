@@ -2,15 +2,27 @@ extern crate self as shuru_cli;
 
 pub mod commands;
 
-use clap::Parser;
+use std::ffi::OsString;
+use std::num::NonZeroUsize;
+
+use clap::{CommandFactory, Parser};
 use shuru_core::{config::Config, error::Error};
-use shuru_tools::task_runner::TaskRunner;
+use shuru_tools::task_runner::{OutputMode, RunOptions, TaskArgs, TaskRunner};
 
 #[derive(Parser)]
 #[clap(version, about = "Shuru task runner", long_about = None)]
 pub struct Cli {
     command: Option<String>,
 
+    #[clap(
+        allow_hyphen_values = true,
+        help = "Task parameters, e.g. --env=staging. Shuru's own flags take precedence"
+    )]
+    params: Vec<String>,
+
+    #[clap(last = true, help = "Extra arguments passed to the task's command")]
+    args: Vec<String>,
+
     #[clap(long = "ai", help = "Start Shuru AI Shell")]
     ai: bool,
 
@@ -31,6 +43,141 @@ pub struct Cli {
 
     #[clap(long = "clear-cache", help = "Clear all cached versions")]
     clear_cache: bool,
+
+    #[clap(
+        short = 'j',
+        long = "jobs",
+        help = "Number of tasks to run in parallel (defaults to the CPU count)"
+    )]
+    jobs: Option<NonZeroUsize>,
+
+    #[clap(long = "force", help = "Run tasks even if their sources are unchanged")]
+    force: bool,
+
+    #[clap(long = "watch", help = "Re-run the task when its files change")]
+    watch: bool,
+
+    #[clap(
+        long = "output",
+        value_name = "MODE",
+        help = "How task output is shown: interleaved, prefixed or grouped"
+    )]
+    output: Option<OutputMode>,
+
+    #[clap(
+        short = 'p',
+        long = "parallel",
+        help = "Run all given tasks at the same time instead of in order"
+    )]
+    parallel: bool,
+
+    #[clap(
+        long = "keep-going",
+        help = "Keep running other tasks after one of them fails"
+    )]
+    keep_going: bool,
+
+    #[clap(
+        long = "timestamps",
+        help = "Add a timestamp to prefixed or grouped output"
+    )]
+    timestamps: bool,
+
+    #[clap(
+        long = "start",
+        value_name = "TASK",
+        help = "Start a service task in the background"
+    )]
+    start: Option<String>,
+
+    #[clap(
+        long = "stop",
+        value_name = "TASK",
+        help = "Stop a service started with --start"
+    )]
+    stop: Option<String>,
+
+    #[clap(long = "status", help = "Show which service tasks are running")]
+    status: bool,
+
+    #[clap(
+        long = "logs",
+        value_name = "TASK",
+        help = "Print the logs of a service started with --start"
+    )]
+    logs: Option<String>,
+
+    #[clap(
+        short = 'f',
+        long = "follow",
+        requires = "logs",
+        help = "Keep printing new lines with --logs"
+    )]
+    follow: bool,
+}
+
+/// Moves shuru's own flags in front of the task names, so they are recognised
+/// anywhere before `--` rather than taken as task parameters, e.g. in
+/// `shuru lint test --keep-going` or `shuru deploy --env=staging -j 2`.
+fn hoist_flags(args: impl IntoIterator<Item = OsString>) -> Vec<OsString> {
+    let mut command = Cli::command();
+    command.build();
+
+    let mut args = args.into_iter();
+    let mut flags: Vec<OsString> = args.next().into_iter().collect();
+    let mut rest = Vec::new();
+
+    while let Some(arg) = args.next() {
+        if arg == "--" {
+            rest.push(arg);
+            rest.extend(args);
+            break;
+        }
+        match arg
+            .to_str()
+            .and_then(|arg| flag_takes_next_value(&command, arg))
+        {
+            Some(takes_value) => {
+                flags.push(arg);
+                if takes_value {
+                    flags.extend(args.next());
+                }
+            }
+            None => rest.push(arg),
+        }
+    }
+
+    flags.extend(rest);
+    flags
+}
+
+/// Whether `arg` is one of shuru's flags and, if so, whether its value is the
+/// next argument rather than part of `arg` (`--output=grouped`, `-j4`).
+fn flag_takes_next_value(command: &clap::Command, arg: &str) -> Option<bool> {
+    let (flag, inline_value) = if let Some(long) = arg.strip_prefix("--") {
+        let (name, value) = match long.split_once('=') {
+            Some((name, value)) => (name, Some(value)),
+            None => (long, None),
+        };
+        let flag = command
+            .get_arguments()
+            .find(|flag| !name.is_empty() && flag.get_long() == Some(name))?;
+        (flag, value.is_some())
+    } else {
+        let short = arg.strip_prefix('-')?;
+        let mut chars = short.chars();
+        let name = chars.next()?;
+        let flag = command
+            .get_arguments()
+            .find(|flag| flag.get_short() == Some(name))?;
+        (flag, !chars.as_str().is_empty())
+    };
+
+    let takes_value = flag.get_action().takes_values();
+    if inline_value && !takes_value {
+        return None;
+    }
+    Some(takes_value && !inline_value)
 }
 
 fn load_config() -> Result<Config, Error> {
@@ -55,7 +202,7 @@ fn load_config() -> Result<Config, Error> {
 }
 
 pub async fn run() -> Result<std::process::ExitStatus, Error> {
-    let cli = Cli::parse();
+    let cli = Cli::parse_from(hoist_flags(std::env::args_os()));
 
     if cli.ai {
         let config = load_config().ok();
@@ -83,10 +230,139 @@ pub async fn run() -> Result<std::process::ExitStatus, Error> {
         return commands::clear_cache();
     }
 
-    let runner = TaskRunner::new(config);
+    if let Some(service) = &cli.stop {
+        return TaskRunner::new(config).stop_service(service);
+    }
+
+    if cli.status {
+        return TaskRunner::new(config).print_service_status();
+    }
+
+    if let Some(service) = &cli.logs {
+        return TaskRunner::new(config).print_service_logs(service, cli.follow);
+    }
+
+    let mut options = RunOptions {
+        force: cli.force,
+        watch: cli.watch,
+        output: cli.output.unwrap_or_default(),
+        timestamps: cli.timestamps,
+        parallel: cli.parallel,
+        keep_going: cli.keep_going,
+        detach: cli.start.is_some(),
+        ..Default::default()
+    };
+    if let Some(jobs) = cli.jobs {
+        options.jobs = jobs;
+    }
+
+    // `shuru lint test build`: every bare word before the first parameter
+    // names another task to run.
+    let mut params = cli.params;
+    let extra_tasks = params
+        .iter()
+        .take_while(|param| !param.starts_with('-'))
+        .count();
+    let mut task_names: Vec<String> = params.drain(..extra_tasks).collect();
+    let task_args = TaskArgs::new(params, cli.args);
+
+    // `shuru db` lists the `db:*` tasks unless `db` is a task itself.
+    if let Some(command_name) = &cli.command {
+        if task_names.is_empty()
+            && config.resolve_task_name(command_name).is_none()
+            && !config.task_group(command_name).is_empty()
+        {
+            return commands::list_group(&config, command_name);
+        }
+    }
+
+    let runner = TaskRunner::with_options(config, options);
+
+    if let Some(service) = &cli.start {
+        return runner.start_service(service, &task_args);
+    }
 
     match cli.command {
-        Some(command_name) => runner.run_task(&command_name),
-        None => runner.run_default(),
+        Some(command_name) => {
+            task_names.insert(0, command_name);
+            runner.run_tasks(&task_names, &task_args)
+        }
+        None => runner.run_default(&task_args),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use shuru_core::task_config::RESERVED_PARAM_NAMES;
+
+    use super::*;
+
+    fn parse(args: &[&str]) -> Cli {
+        Cli::try_parse_from(hoist_flags(args.iter().map(OsString::from))).unwrap()
+    }
+
+    #[test]
+    fn flags_before_task_parameters() {
+        let cli = parse(&["shuru", "--force", "-j", "2", "deploy", "--env=staging"]);
+        assert!(cli.force);
+        assert_eq!(cli.jobs, NonZeroUsize::new(2));
+        assert_eq!(cli.command.as_deref(), Some("deploy"));
+        assert_eq!(cli.params, ["--env=staging"]);
+    }
+
+    #[test]
+    fn flags_after_task_parameters() {
+        let cli = parse(&["shuru", "deploy", "--env=staging", "--force", "-j", "2"]);
+        assert!(cli.force);
+        assert_eq!(cli.jobs, NonZeroUsize::new(2));
+        assert_eq!(cli.command.as_deref(), Some("deploy"));
+        assert_eq!(cli.params, ["--env=staging"]);
+    }
+
+    #[test]
+    fn flags_with_attached_values() {
+        let cli = parse(&["shuru", "build", "-j4", "--output=grouped"]);
+        assert_eq!(cli.jobs, NonZeroUsize::new(4));
+        assert_eq!(cli.output, Some(OutputMode::Grouped));
+        assert!(cli.params.is_empty());
+    }
+
+    #[test]
+    fn flags_after_several_tasks() {
+        let cli = parse(&["shuru", "lint", "test", "--keep-going", "-p"]);
+        assert!(cli.keep_going);
+        assert!(cli.parallel);
+        assert_eq!(cli.command.as_deref(), Some("lint"));
+        assert_eq!(cli.params, ["test"]);
+    }
+
+    #[test]
+    fn unknown_flags_stay_task_parameters() {
+        let cli = parse(&["shuru", "deploy", "--verbose", "--env", "staging"]);
+        assert_eq!(cli.params, ["--verbose", "--env", "staging"]);
+    }
+
+    #[test]
+    fn arguments_after_double_dash_are_untouched() {
+        let cli = parse(&["shuru", "test", "--", "--force", "-p"]);
+        assert!(!cli.force);
+        assert!(!cli.parallel);
+        assert_eq!(cli.args, ["--force", "-p"]);
+    }
+
+    #[test]
+    fn every_long_flag_is_a_reserved_param_name() {
+        let command = Cli::command();
+        let mut names: Vec<&str> = command
+            .get_arguments()
+            .filter_map(|flag| flag.get_long())
+            .chain(["help", "version"])
+            .collect();
+        names.sort_unstable();
+        names.dedup();
+
+        let mut reserved = RESERVED_PARAM_NAMES.to_vec();
+        reserved.sort_unstable();
+        assert_eq!(names, reserved);
     }
 }
@@ -1,13 +1,21 @@
 use serde::{Deserialize, Serialize};
 
-use shuru_core::error::Error;
-use shuru_core::task_config::TaskConfig;
+use shuru_core::error::{ConfigValidationError, Error};
+use shuru_core::task_config::{ShellName, TaskConfig};
+use shuru_core::utils::dotenv::{find_reference_cycle, interpolate_all, load_env_file};
 use shuru_core::version_config::{deserialize_versions, VersionInfo, VersionedCommand};
 
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
 
 #[derive(Debug, Default, Clone, Deserialize, Serialize)]
 pub struct Config {
+    #[serde(default)]
+    pub shell: Option<ShellName>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub env_file: Vec<String>,
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub env: HashMap<String, String>,
     #[serde(default)]
     pub tasks: HashMap<String, TaskConfig>,
     #[serde(default, deserialize_with = "deserialize_versions")]
@@ -15,15 +23,225 @@ pub struct Config {
 }
 
 impl Config {
+    /// Variables shared by every task: the project `env_file`s, overridden by
+    /// the top-level `[env]` table, whose values can reference `${VAR}`
+    /// from the files, the process or each other.
+    pub fn project_env(&self, project_dir: &Path) -> Result<HashMap<String, String>, Error> {
+        let mut env = HashMap::new();
+
+        for file in &self.env_file {
+            load_env_file(&project_dir.join(file), &mut env)?;
+        }
+
+        let inline = interpolate_all(&self.env, &env);
+        env.extend(inline);
+
+        Ok(env)
+    }
+
     pub fn validate_tasks(&self) -> Result<(), Error> {
+        if let Some(cycle) = find_reference_cycle(&self.env) {
+            return Err(ConfigValidationError::EnvReferenceCycleError {
+                scope: "the project".to_string(),
+                cycle,
+            }
+            .into());
+        }
         for (task_name, task_config) in &self.tasks {
             task_config.validate(task_name)?;
             for dep in &task_config.depends {
-                if !self.tasks.contains_key(dep) {
+                if self.resolve_task_name(dep).is_none() {
                     return Err(Error::CommandNotFound(dep.to_string()));
                 }
             }
         }
+        self.validate_aliases()?;
+        self.validate_dependency_cycles()?;
+        Ok(())
+    }
+
+    /// Looks a task up by its name or one of its aliases and returns the
+    /// task's name.
+    pub fn resolve_task_name<'a>(&'a self, name: &str) -> Option<&'a str> {
+        if let Some((task_name, _)) = self.tasks.get_key_value(name) {
+            return Some(task_name);
+        }
+        self.tasks
+            .iter()
+            .find(|(_, task)| task.aliases.iter().any(|alias| alias == name))
+            .map(|(task_name, _)| task_name.as_str())
+    }
+
+    /// Names of the tasks `task` depends on, which may be given by alias in
+    /// `depends`. Unknown names are returned as they are.
+    pub fn dependencies<'a>(&'a self, task: &'a TaskConfig) -> impl Iterator<Item = &'a str> {
+        task.depends
+            .iter()
+            .map(|dep| self.resolve_task_name(dep).unwrap_or(dep))
+    }
+
+    /// Names of the tasks that can be run directly, sorted.
+    pub fn public_task_names(&self) -> Vec<&str> {
+        let mut names: Vec<&str> = self
+            .tasks
+            .iter()
+            .filter(|(name, task)| !task.is_private(name))
+            .map(|(name, _)| name.as_str())
+            .collect();
+        names.sort();
+        names
+    }
+
+    /// Names of the public tasks inside the `group:` namespace, sorted.
+    pub fn task_group(&self, group: &str) -> Vec<&str> {
+        let prefix = format!("{}:", group);
+        self.public_task_names()
+            .into_iter()
+            .filter(|name| name.starts_with(&prefix))
+            .collect()
+    }
+
+    fn validate_aliases(&self) -> Result<(), ConfigValidationError> {
+        let mut task_names: Vec<&String> = self.tasks.keys().collect();
+        task_names.sort();
+
+        let mut seen: HashMap<&str, &str> = HashMap::new();
+        for task_name in task_names {
+            for alias in &self.tasks[task_name].aliases {
+                let conflict = |conflict: String| ConfigValidationError::AliasConflictError {
+                    task: task_name.to_string(),
+                    alias: alias.to_string(),
+                    conflict,
+                };
+
+                if alias.is_empty() {
+                    return Err(conflict("an empty name".to_string()));
+                }
+                if self.tasks.contains_key(alias) {
+                    return Err(conflict(format!("task '{}'", alias)));
+                }
+                if let Some(other) = seen.insert(alias, task_name) {
+                    return Err(conflict(format!("an alias of task '{}'", other)));
+                }
+            }
+        }
         Ok(())
     }
+
+    fn validate_dependency_cycles(&self) -> Result<(), ConfigValidationError> {
+        let mut task_names: Vec<&str> = self.tasks.keys().map(String::as_str).collect();
+        task_names.sort();
+
+        let mut visited = HashSet::new();
+        for task_name in task_names {
+            self.find_dependency_cycle(task_name, &mut visited, &mut Vec::new())?;
+        }
+        Ok(())
+    }
+
+    fn find_dependency_cycle<'a>(
+        &'a self,
+        task_name: &'a str,
+        visited: &mut HashSet<&'a str>,
+        path: &mut Vec<&'a str>,
+    ) -> Result<(), ConfigValidationError> {
+        if let Some(position) = path.iter().position(|name| *name == task_name) {
+            let mut cycle = path[position..].to_vec();
+            cycle.push(task_name);
+            return Err(ConfigValidationError::DependencyCycleError(
+                cycle.join(" -> "),
+            ));
+        }
+
+        if !visited.insert(task_name) {
+            return Ok(());
+        }
+
+        if let Some(task) = self.tasks.get(task_name) {
+            path.push(task_name);
+            for dep in self.dependencies(task) {
+                self.find_dependency_cycle(dep, visited, path)?;
+            }
+            path.pop();
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(toml: &str) -> Config {
+        toml::from_str(toml).unwrap()
+    }
+
+    #[test]
+    fn accepts_acyclic_dependencies() {
+        let config = config(
+            r#"
+            [tasks.build]
+            command = "cargo build"
+            [tasks.test]
+            command = "cargo test"
+            depends = ["build"]
+            [tasks.ci]
+            command = "true"
+            depends = ["build", "test"]
+            "#,
+        );
+        assert!(config.validate_tasks().is_ok());
+    }
+
+    #[test]
+    fn detects_dependency_cycles() {
+        let config = config(
+            r#"
+            [tasks.a]
+            command = "true"
+            depends = ["b"]
+            [tasks.b]
+            command = "true"
+            depends = ["c"]
+            [tasks.c]
+            command = "true"
+            depends = ["a"]
+            "#,
+        );
+        let error = config.validate_tasks().unwrap_err().to_string();
+        assert!(error.contains("a -> b -> c -> a"), "{}", error);
+    }
+
+    #[test]
+    fn detects_cycles_through_aliases() {
+        let config = config(
+            r#"
+            [tasks.build]
+            command = "true"
+            aliases = ["b"]
+            depends = ["t"]
+            [tasks.test]
+            command = "true"
+            aliases = ["t"]
+            depends = ["b"]
+            "#,
+        );
+        let error = config.validate_tasks().unwrap_err().to_string();
+        assert!(error.contains("build -> test -> build"), "{}", error);
+    }
+
+    #[test]
+    fn rejects_unknown_dependencies() {
+        let config = config(
+            r#"
+            [tasks.ci]
+            command = "true"
+            depends = ["missing"]
+            "#,
+        );
+        assert!(matches!(
+            config.validate_tasks(),
+            Err(Error::CommandNotFound(name)) if name == "missing"
+        ));
+    }
 }
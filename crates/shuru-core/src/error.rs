@@ -5,8 +5,64 @@ pub enum ConfigValidationError {
     #[error("Description: Command cannot be empty for task: '{0}'.")]
     EmptyCommandError(String),
 
+    #[error("Description: Commands list cannot be empty for task: '{0}'.")]
+    EmptyCommandsError(String),
+
+    #[error("Description: Command step {1} cannot be empty for task: '{0}'.")]
+    EmptyCommandStepError(String, usize),
+
+    #[error("Description: Exec cannot be empty for task: '{0}'.")]
+    EmptyExecError(String),
+
+    #[error("Description: The program of `exec` in task '{0}' cannot be `{{{{args}}}}`.\n    Hint: Name the program first, e.g. `exec = [\"cargo\", \"{{{{args}}}}\"]`.")]
+    ExecArgsProgramError(String),
+
+    #[error("Description: Task '{0}' must define only one of `command`, `commands` or `exec`.")]
+    ConflictingCommandError(String),
+
     #[error("Description: Directory cannot be empty for task: '{0}'.")]
     EmptyDirError(String),
+
+    #[error("Description: Alias '{alias}' of task '{task}' conflicts with {conflict}.")]
+    AliasConflictError {
+        task: String,
+        alias: String,
+        conflict: String,
+    },
+
+    #[error("Description: Dependency cycle detected: {0}")]
+    DependencyCycleError(String),
+
+    #[error("Description: Variables in the `env` of {scope} reference each other: {cycle}")]
+    EnvReferenceCycleError { scope: String, cycle: String },
+
+    #[error("Description: Invalid glob pattern '{pattern}' for task '{task}': {reason}")]
+    InvalidGlobError {
+        task: String,
+        pattern: String,
+        reason: String,
+    },
+
+    #[error("Description: Invalid {field} '{value}' for task '{task}': {reason}")]
+    InvalidDurationError {
+        task: String,
+        field: String,
+        value: String,
+        reason: String,
+    },
+
+    #[error("Description: Invalid `if` condition for task '{task}': {reason}")]
+    InvalidConditionError { task: String, reason: String },
+
+    #[error("Description: Invalid service task '{task}': {reason}")]
+    InvalidServiceError { task: String, reason: String },
+
+    #[error("Description: Invalid parameter '{param}' for task '{task}': {reason}")]
+    InvalidParamError {
+        task: String,
+        param: String,
+        reason: String,
+    },
 }
 
 #[derive(Debug, Error)]
@@ -166,6 +222,51 @@ pub enum Error {
     #[error("Command '{0}' not found. Did you mean: {1}?")]
     CommandNotFoundWithSuggestions(String, String),
 
+    #[error("Task '{0}' is private and can only run as a dependency of another task.")]
+    PrivateTaskError(String),
+
+    #[error("Task parameter error\n    {0}")]
+    TaskParamError(String),
+
+    #[error("Task '{task}' failed with exit code {code}.")]
+    TaskFailed { task: String, code: i32 },
+
+    #[error("Task '{task}' failed at step {step} of {steps} with exit code {code}.")]
+    TaskStepFailed {
+        task: String,
+        step: usize,
+        steps: usize,
+        code: i32,
+    },
+
+    #[error("Task '{task}' failed in its `{hook}` hook with exit code {code}.")]
+    TaskHookFailed {
+        task: String,
+        hook: String,
+        code: i32,
+    },
+
+    #[error("Service '{task}' was not ready after {timeout}.\n    Hint: Check its output and the `ready` check of the task.")]
+    ServiceNotReady { task: String, timeout: String },
+
+    #[error("Service '{task}' exited with code {code} before it was ready.")]
+    ServiceExited { task: String, code: i32 },
+
+    #[error("Task '{0}' is not a service.\n    Hint: Add `service = true` to the task to run it in the background.")]
+    NotAService(String),
+
+    #[error("Interrupted by signal {0}.")]
+    Interrupted(i32),
+
+    #[error("Task '{task}' timed out after {timeout} and was terminated.")]
+    TaskTimedOut { task: String, timeout: String },
+
+    #[error("Failed to load env file '{path}'\n    Technical: {reason}")]
+    EnvFileError { path: String, reason: String },
+
+    #[error("Shell '{0}' was not found on PATH.\n    Hint: Install it or choose another `shell` in shuru.toml.")]
+    ShellNotFound(String),
+
     #[error("Version manager error\n    {0}")]
     VersionManagerError(#[from] VersionManagerError),
 
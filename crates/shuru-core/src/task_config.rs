@@ -1,33 +1,567 @@
 use serde::{Deserialize, Serialize};
 use shuru_core::error::ConfigValidationError;
+use shuru_core::utils::dotenv::find_reference_cycle;
+use shuru_core::utils::duration::parse_duration;
 use std::collections::HashMap;
+use std::time::Duration;
+
+/// Name of the placeholder that is replaced by the extra arguments given
+/// after `--` on the command line. It cannot be used as a parameter name.
+pub const ARGS_PARAM: &str = "args";
+pub const ARGS_PLACEHOLDER: &str = "{{args}}";
+/// Long flags of the shuru CLI, which are taken by shuru before task
+/// parameters, so parameters cannot use their names.
+pub const RESERVED_PARAM_NAMES: &[&str] = &[
+    "ai",
+    "completions",
+    "list-commands",
+    "update-versions",
+    "clear-cache",
+    "jobs",
+    "force",
+    "watch",
+    "output",
+    "parallel",
+    "keep-going",
+    "timestamps",
+    "start",
+    "stop",
+    "status",
+    "logs",
+    "follow",
+    "help",
+    "version",
+];
 
 #[derive(Debug, Serialize, Deserialize, Default, Clone)]
 pub struct TaskConfig {
+    #[serde(default, skip_serializing_if = "String::is_empty")]
     pub command: String,
+    #[serde(default)]
+    pub commands: Option<Vec<String>>,
+    #[serde(default)]
+    pub exec: Option<Vec<String>>,
     pub dir: Option<String>,
     pub default: Option<bool>,
+    pub private: Option<bool>,
+    pub allow_failure: Option<bool>,
+    pub service: Option<bool>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub aliases: Vec<String>,
     #[serde(default)]
     pub depends: Vec<String>,
     #[serde(default)]
     pub description: Option<String>,
     #[serde(default)]
+    pub run: Option<RunPolicy>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub sources: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub outputs: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub watch: Vec<String>,
+    #[serde(default)]
+    pub shell: Option<ShellName>,
+    #[serde(default)]
+    pub timeout: Option<String>,
+    #[serde(default)]
+    pub retries: Option<u32>,
+    #[serde(default)]
+    pub retry_delay: Option<String>,
+    #[serde(default)]
+    pub retry_backoff: Option<RetryBackoff>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub platforms: Vec<Platform>,
+    #[serde(default)]
+    pub before: Option<String>,
+    #[serde(default)]
+    pub after: Option<String>,
+    #[serde(default)]
+    pub on_failure: Option<String>,
+    #[serde(default)]
+    pub finally: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub env_file: Vec<String>,
+    #[serde(default)]
     pub env: HashMap<String, String>,
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub params: HashMap<String, TaskParam>,
+    #[serde(default, rename = "if", skip_serializing_if = "Option::is_none")]
+    pub condition: Option<TaskCondition>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ready: Option<ReadyProbe>,
+}
+
+/// How long a service gets to pass its `ready` check when no `timeout` is set.
+pub const DEFAULT_READY_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Controls how often a task runs within a single shuru invocation.
+#[derive(Debug, Serialize, Deserialize, Default, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum RunPolicy {
+    /// Run at most once, reusing the result for every other dependent.
+    #[default]
+    Once,
+    /// Run every time the task is requested, even as a shared dependency.
+    Always,
+}
+
+/// Operating systems a task can be limited to with `platforms`.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Platform {
+    Linux,
+    #[serde(alias = "darwin")]
+    Macos,
+    #[serde(alias = "win")]
+    Windows,
+}
+
+impl Platform {
+    pub fn current() -> Self {
+        match shuru_core::utils::os_type() {
+            "darwin" => Platform::Macos,
+            "win" => Platform::Windows,
+            _ => Platform::Linux,
+        }
+    }
+}
+
+impl std::fmt::Display for Platform {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Platform::Linux => write!(f, "linux"),
+            Platform::Macos => write!(f, "macos"),
+            Platform::Windows => write!(f, "windows"),
+        }
+    }
+}
+
+/// Checks evaluated right before a task runs. Every check that is set has to
+/// pass, otherwise the task is skipped.
+#[derive(Debug, Serialize, Deserialize, Default, Clone)]
+pub struct TaskCondition {
+    /// Environment variable that has to be set (and non-empty).
+    #[serde(default)]
+    pub env: Option<String>,
+    /// Value the `env` variable has to be equal to.
+    #[serde(default)]
+    pub equals: Option<String>,
+    /// File or directory, relative to the task's `dir`, that has to exist.
+    #[serde(default)]
+    pub file: Option<String>,
+    /// Command line that has to exit successfully.
+    #[serde(default)]
+    pub command: Option<String>,
+}
+
+impl TaskCondition {
+    fn validate(&self, task_name: &str) -> Result<(), ConfigValidationError> {
+        let invalid = |reason: &str| {
+            Err(ConfigValidationError::InvalidConditionError {
+                task: task_name.to_string(),
+                reason: reason.to_string(),
+            })
+        };
+
+        if self.env.is_none() && self.file.is_none() && self.command.is_none() {
+            return invalid("expected at least one of `env`, `file` or `command`");
+        }
+        if self.equals.is_some() && self.env.is_none() {
+            return invalid("`equals` requires `env`");
+        }
+        if [&self.env, &self.file, &self.command]
+            .iter()
+            .any(|value| value.as_deref().is_some_and(str::is_empty))
+        {
+            return invalid("`env`, `file` and `command` must not be empty");
+        }
+        Ok(())
+    }
+}
+
+/// Check telling that a `service` task is ready for its dependents. Exactly
+/// one of `port`, `http`, `log` or `command` has to be set.
+#[derive(Debug, Serialize, Deserialize, Default, Clone)]
+pub struct ReadyProbe {
+    /// Local TCP port that has to accept connections.
+    #[serde(default)]
+    pub port: Option<u16>,
+    /// URL that has to answer with a successful (2xx) status.
+    #[serde(default)]
+    pub http: Option<String>,
+    /// Regular expression matched against every line the service prints.
+    #[serde(default)]
+    pub log: Option<String>,
+    /// Command line that has to exit successfully.
+    #[serde(default)]
+    pub command: Option<String>,
+    /// How long to wait for the check to pass.
+    #[serde(default)]
+    pub timeout: Option<String>,
+}
+
+impl ReadyProbe {
+    /// The parsed `timeout`, [`DEFAULT_READY_TIMEOUT`] when unset.
+    pub fn timeout(&self) -> Duration {
+        self.timeout
+            .as_deref()
+            .and_then(|timeout| parse_duration(timeout).ok())
+            .unwrap_or(DEFAULT_READY_TIMEOUT)
+    }
+
+    fn validate(&self, task_name: &str) -> Result<(), ConfigValidationError> {
+        let invalid = |reason: String| {
+            Err(ConfigValidationError::InvalidServiceError {
+                task: task_name.to_string(),
+                reason,
+            })
+        };
+
+        let checks = [
+            self.port.is_some(),
+            self.http.is_some(),
+            self.log.is_some(),
+            self.command.is_some(),
+        ];
+        if checks.iter().filter(|set| **set).count() != 1 {
+            return invalid(
+                "`ready` needs exactly one of `port`, `http`, `log` or `command`".to_string(),
+            );
+        }
+        if [&self.http, &self.log, &self.command]
+            .iter()
+            .any(|value| value.as_deref().is_some_and(str::is_empty))
+        {
+            return invalid("`http`, `log` and `command` must not be empty".to_string());
+        }
+        if let Some(url) = &self.http {
+            if !url.starts_with("http://") {
+                return invalid(format!("`http` must be an http:// URL, got '{}'", url));
+            }
+        }
+        if let Some(pattern) = &self.log {
+            if let Err(e) = regex::Regex::new(pattern) {
+                return invalid(format!("invalid `log` pattern '{}': {}", pattern, e));
+            }
+        }
+        if let Some(timeout) = &self.timeout {
+            parse_duration(timeout).map_err(|reason| {
+                ConfigValidationError::InvalidDurationError {
+                    task: task_name.to_string(),
+                    field: "ready.timeout".to_string(),
+                    value: timeout.to_string(),
+                    reason,
+                }
+            })?;
+        }
+        Ok(())
+    }
+}
+
+/// How the delay between retries of a failing task evolves.
+#[derive(Debug, Serialize, Deserialize, Default, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum RetryBackoff {
+    /// Wait `retry_delay` before every retry.
+    #[default]
+    Fixed,
+    /// Double the delay after every retry.
+    Exponential,
+}
+
+/// Shell used to run a task's command lines.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ShellName {
+    Sh,
+    Bash,
+    Zsh,
+    Fish,
+    Dash,
+    Pwsh,
+    Nu,
+}
+
+/// A named parameter filled from `--name=value` on the command line and
+/// substituted into `{{name}}` placeholders of the command, dir and env.
+#[derive(Debug, Serialize, Deserialize, Default, Clone)]
+pub struct TaskParam {
+    #[serde(default, rename = "type")]
+    pub kind: ParamType,
+    #[serde(default)]
+    pub default: Option<ParamValue>,
+    #[serde(default)]
+    pub required: Option<bool>,
+    #[serde(default)]
+    pub help: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub values: Vec<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Default, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ParamType {
+    #[default]
+    String,
+    Bool,
+    Enum,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+#[serde(untagged)]
+pub enum ParamValue {
+    Bool(bool),
+    String(String),
+}
+
+impl std::fmt::Display for ParamValue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ParamValue::Bool(value) => write!(f, "{}", value),
+            ParamValue::String(value) => write!(f, "{}", value),
+        }
+    }
+}
+
+impl TaskParam {
+    pub fn is_required(&self) -> bool {
+        self.required.unwrap_or(false)
+    }
+
+    /// Describes how to pass the parameter, e.g. `--env=<staging|production>`.
+    pub fn usage(&self, name: &str) -> String {
+        let value = match self.kind {
+            ParamType::String => "=<value>".to_string(),
+            ParamType::Bool => String::new(),
+            ParamType::Enum => format!("=<{}>", self.values.join("|")),
+        };
+
+        match &self.help {
+            Some(help) => format!("--{}{}  {}", name, value, help),
+            None => format!("--{}{}", name, value),
+        }
+    }
+
+    fn validate(&self, task_name: &str, name: &str) -> Result<(), ConfigValidationError> {
+        let invalid = |reason: String| {
+            Err(ConfigValidationError::InvalidParamError {
+                task: task_name.to_string(),
+                param: name.to_string(),
+                reason,
+            })
+        };
+
+        if name.is_empty()
+            || !name
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+        {
+            return invalid("names may only contain letters, digits, '-' and '_'.".to_string());
+        }
+
+        if name == ARGS_PARAM {
+            return invalid("the name is reserved for extra command arguments.".to_string());
+        }
+
+        if RESERVED_PARAM_NAMES.contains(&name) {
+            return invalid(format!(
+                "`--{}` is a shuru flag, so the parameter could never be passed.",
+                name
+            ));
+        }
+
+        match self.kind {
+            ParamType::Enum if self.values.is_empty() => {
+                return invalid("enum parameters need a non-empty `values` list.".to_string());
+            }
+            ParamType::String | ParamType::Bool if !self.values.is_empty() => {
+                return invalid("`values` is only allowed for enum parameters.".to_string());
+            }
+            _ => {}
+        }
+
+        match (&self.default, self.kind) {
+            (Some(_), _) if self.is_required() => {
+                invalid("a required parameter cannot have a default.".to_string())
+            }
+            (Some(ParamValue::String(_)), ParamType::Bool) => {
+                invalid("the default of a bool parameter must be true or false.".to_string())
+            }
+            (Some(ParamValue::Bool(_)), ParamType::String | ParamType::Enum) => {
+                invalid("the default must be a string.".to_string())
+            }
+            (Some(ParamValue::String(default)), ParamType::Enum)
+                if !self.values.contains(default) =>
+            {
+                invalid(format!(
+                    "the default '{}' is not one of: {}.",
+                    default,
+                    self.values.join(", ")
+                ))
+            }
+            _ => Ok(()),
+        }
+    }
 }
 
 impl TaskConfig {
+    /// The shell command lines of the task, in order: the `commands` list
+    /// when present, otherwise the single `command`. Empty for `exec` tasks.
+    pub fn steps(&self) -> Vec<&str> {
+        match (&self.commands, &self.exec) {
+            (Some(commands), _) => commands.iter().map(String::as_str).collect(),
+            (None, Some(_)) => Vec::new(),
+            (None, None) => vec![self.command.as_str()],
+        }
+    }
+
+    /// A one-line summary of what the task runs, for display purposes.
+    pub fn command_summary(&self) -> String {
+        match &self.exec {
+            Some(exec) => exec.join(" "),
+            None => self.steps().join(" && "),
+        }
+    }
+
+    /// Private tasks, marked with `private = true` or a leading `_` in their
+    /// name (or its last `group:` segment), can only run as a dependency of
+    /// another task.
+    pub fn is_private(&self, task_name: &str) -> bool {
+        self.private.unwrap_or(false)
+            || task_name
+                .rsplit(':')
+                .next()
+                .is_some_and(|name| name.starts_with('_'))
+    }
+
+    /// Services keep running in the background while the tasks depending on
+    /// them run, and are stopped once the requested task finished.
+    pub fn is_service(&self) -> bool {
+        self.service.unwrap_or(false)
+    }
+
+    pub fn runs_always(&self) -> bool {
+        self.run.unwrap_or_default() == RunPolicy::Always
+    }
+
+    pub fn supports_current_platform(&self) -> bool {
+        self.platforms.is_empty() || self.platforms.contains(&Platform::current())
+    }
+
+    /// The parsed `timeout`. Invalid values are rejected by validation.
+    pub fn timeout(&self) -> Option<Duration> {
+        self.timeout
+            .as_deref()
+            .and_then(|timeout| parse_duration(timeout).ok())
+    }
+
+    /// The parsed `retry_delay`, zero when unset.
+    pub fn retry_delay(&self) -> Duration {
+        self.retry_delay
+            .as_deref()
+            .and_then(|delay| parse_duration(delay).ok())
+            .unwrap_or_default()
+    }
+
     pub fn validate(&self, task_name: &str) -> Result<(), ConfigValidationError> {
         self.validate_command(task_name)?;
         self.validate_dir(task_name)?;
+        self.validate_globs(task_name)?;
+        self.validate_env(task_name)?;
+        self.validate_params(task_name)?;
+        self.validate_durations(task_name)?;
+        if let Some(condition) = &self.condition {
+            condition.validate(task_name)?;
+        }
+        self.validate_service(task_name)?;
         Ok(())
     }
 
+    fn validate_service(&self, task_name: &str) -> Result<(), ConfigValidationError> {
+        let invalid = |reason: String| {
+            Err(ConfigValidationError::InvalidServiceError {
+                task: task_name.to_string(),
+                reason,
+            })
+        };
+
+        if !self.is_service() {
+            if self.ready.is_some() {
+                return invalid("`ready` requires `service = true`".to_string());
+            }
+            return Ok(());
+        }
+
+        let unsupported = [
+            ("commands", self.commands.is_some()),
+            ("watch", !self.watch.is_empty()),
+            ("sources", !self.sources.is_empty()),
+            ("timeout", self.timeout.is_some()),
+            ("retries", self.retries.is_some()),
+            ("before", self.before.is_some()),
+            ("after", self.after.is_some()),
+            ("on_failure", self.on_failure.is_some()),
+            ("finally", self.finally.is_some()),
+        ];
+        if let Some((field, _)) = unsupported.iter().find(|(_, set)| *set) {
+            return invalid(format!("services cannot use `{}`", field));
+        }
+
+        match &self.ready {
+            Some(ready) => ready.validate(task_name),
+            None => Ok(()),
+        }
+    }
+
     fn validate_command(&self, task_name: &str) -> Result<(), ConfigValidationError> {
-        if self.command.is_empty() {
-            return Err(ConfigValidationError::EmptyCommandError(
+        let defined = [
+            !self.command.is_empty(),
+            self.commands.is_some(),
+            self.exec.is_some(),
+        ];
+        if defined.iter().filter(|defined| **defined).count() > 1 {
+            return Err(ConfigValidationError::ConflictingCommandError(
                 task_name.to_string(),
             ));
         }
+
+        if let Some(exec) = &self.exec {
+            if exec.first().is_none_or(|program| program.is_empty()) {
+                return Err(ConfigValidationError::EmptyExecError(task_name.to_string()));
+            }
+            // Without extra arguments the placeholder expands to nothing,
+            // leaving no program to run.
+            if exec[0] == ARGS_PLACEHOLDER {
+                return Err(ConfigValidationError::ExecArgsProgramError(
+                    task_name.to_string(),
+                ));
+            }
+            return Ok(());
+        }
+
+        let Some(commands) = &self.commands else {
+            if self.command.is_empty() {
+                return Err(ConfigValidationError::EmptyCommandError(
+                    task_name.to_string(),
+                ));
+            }
+            return Ok(());
+        };
+
+        if commands.is_empty() {
+            return Err(ConfigValidationError::EmptyCommandsError(
+                task_name.to_string(),
+            ));
+        }
+
+        if let Some(position) = commands.iter().position(|step| step.trim().is_empty()) {
+            return Err(ConfigValidationError::EmptyCommandStepError(
+                task_name.to_string(),
+                position + 1,
+            ));
+        }
+
         Ok(())
     }
 
@@ -39,4 +573,60 @@ impl TaskConfig {
         }
         Ok(())
     }
+
+    fn validate_globs(&self, task_name: &str) -> Result<(), ConfigValidationError> {
+        for pattern in self.sources.iter().chain(&self.outputs).chain(&self.watch) {
+            glob::Pattern::new(pattern).map_err(|e| ConfigValidationError::InvalidGlobError {
+                task: task_name.to_string(),
+                pattern: pattern.to_string(),
+                reason: e.to_string(),
+            })?;
+        }
+        Ok(())
+    }
+
+    fn validate_env(&self, task_name: &str) -> Result<(), ConfigValidationError> {
+        match find_reference_cycle(&self.env) {
+            Some(cycle) => Err(ConfigValidationError::EnvReferenceCycleError {
+                scope: format!("task '{}'", task_name),
+                cycle,
+            }),
+            None => Ok(()),
+        }
+    }
+
+    fn validate_params(&self, task_name: &str) -> Result<(), ConfigValidationError> {
+        for (name, param) in &self.params {
+            param.validate(task_name, name)?;
+        }
+        Ok(())
+    }
+
+    fn validate_durations(&self, task_name: &str) -> Result<(), ConfigValidationError> {
+        if let Some(timeout) = &self.timeout {
+            match parse_duration(timeout) {
+                Ok(duration) if duration.is_zero() => {
+                    Err("timeout must be greater than zero".to_string())
+                }
+                result => result.map(|_| ()),
+            }
+            .map_err(|reason| ConfigValidationError::InvalidDurationError {
+                task: task_name.to_string(),
+                field: "timeout".to_string(),
+                value: timeout.to_string(),
+                reason,
+            })?;
+        }
+        if let Some(delay) = &self.retry_delay {
+            parse_duration(delay).map_err(|reason| {
+                ConfigValidationError::InvalidDurationError {
+                    task: task_name.to_string(),
+                    field: "retry_delay".to_string(),
+                    value: delay.to_string(),
+                    reason,
+                }
+            })?;
+        }
+        Ok(())
+    }
 }
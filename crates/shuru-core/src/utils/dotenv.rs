@@ -0,0 +1,308 @@
+use std::collections::HashMap;
+use std::path::Path;
+
+use shuru_core::error::Error;
+
+/// Reads a dotenv file into `env`, overriding existing keys. Values may
+/// reference `${VAR}` from earlier lines, from `env` or from the process
+/// environment; single-quoted values are taken literally. Missing files are
+/// skipped, so optional files such as `.env.local` need not exist.
+pub fn load_env_file(path: &Path, env: &mut HashMap<String, String>) -> Result<(), Error> {
+    let env_file_error = |reason: String| Error::EnvFileError {
+        path: path.display().to_string(),
+        reason,
+    };
+
+    let content = match std::fs::read_to_string(path) {
+        Ok(content) => content,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(()),
+        Err(e) => return Err(env_file_error(e.to_string())),
+    };
+
+    for (index, line) in content.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let line = line.strip_prefix("export ").unwrap_or(line);
+        let Some((key, value)) = line.split_once('=') else {
+            return Err(env_file_error(format!(
+                "line {}: expected KEY=VALUE",
+                index + 1
+            )));
+        };
+
+        let key = key.trim();
+        if key.is_empty() || !key.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {
+            return Err(env_file_error(format!(
+                "line {}: invalid variable name '{}'",
+                index + 1,
+                key
+            )));
+        }
+
+        let value = parse_value(value.trim(), env)
+            .map_err(|reason| env_file_error(format!("line {}: {}", index + 1, reason)))?;
+        env.insert(key.to_string(), value);
+    }
+
+    Ok(())
+}
+
+/// Replaces `${VAR}` with its value from `env`, falling back to the process
+/// environment. Unknown variables expand to an empty string.
+pub fn interpolate(value: &str, env: &HashMap<String, String>) -> String {
+    expand(value, &mut |name| lookup(name, env))
+}
+
+/// Interpolates a table of inline variables on top of `env`. Values can
+/// reference each other regardless of the order they are declared in; a
+/// variable referencing itself gets its value from `env` or the process
+/// environment, e.g. `PATH = "${PATH}:bin"`. Cycles between variables are
+/// rejected by [`find_reference_cycle`] when the config is validated.
+pub fn interpolate_all(
+    values: &HashMap<String, String>,
+    env: &HashMap<String, String>,
+) -> HashMap<String, String> {
+    let mut resolved = HashMap::new();
+    for key in values.keys() {
+        resolve(key, values, env, &mut resolved, &mut Vec::new());
+    }
+    resolved
+}
+
+fn resolve(
+    key: &str,
+    values: &HashMap<String, String>,
+    env: &HashMap<String, String>,
+    resolved: &mut HashMap<String, String>,
+    resolving: &mut Vec<String>,
+) -> String {
+    if let Some(value) = resolved.get(key) {
+        return value.clone();
+    }
+
+    resolving.push(key.to_string());
+    let value = expand(&values[key], &mut |name| match values.contains_key(name)
+        && !resolving.iter().any(|key| key == name)
+    {
+        true => resolve(name, values, env, resolved, resolving),
+        false => lookup(name, env),
+    });
+    resolving.pop();
+
+    resolved.insert(key.to_string(), value.clone());
+    value
+}
+
+/// Finds variables of an inline table that reference each other in a
+/// cycle, e.g. `A -> B -> A`. A variable referencing only itself is fine.
+pub fn find_reference_cycle(values: &HashMap<String, String>) -> Option<String> {
+    let mut keys: Vec<&String> = values.keys().collect();
+    keys.sort();
+
+    fn visit<'a>(
+        key: &'a str,
+        values: &'a HashMap<String, String>,
+        path: &mut Vec<&'a str>,
+    ) -> Option<String> {
+        if let Some(position) = path.iter().position(|name| *name == key) {
+            let mut cycle = path[position..].to_vec();
+            cycle.push(key);
+            return Some(cycle.join(" -> "));
+        }
+
+        path.push(key);
+        let mut names: Vec<&str> = references(&values[key])
+            .filter(|name| *name != key && values.contains_key(*name))
+            .collect();
+        names.sort();
+        let cycle = names
+            .into_iter()
+            .find_map(|name| visit(values.get_key_value(name)?.0, values, path));
+        path.pop();
+        cycle
+    }
+
+    keys.into_iter()
+        .find_map(|key| visit(key, values, &mut Vec::new()))
+}
+
+/// Names of the `${VAR}` references in `value`.
+fn references(value: &str) -> impl Iterator<Item = &str> {
+    value.split("${").skip(1).filter_map(|part| {
+        let end = part.find('}')?;
+        Some(&part[..end])
+    })
+}
+
+fn lookup(name: &str, env: &HashMap<String, String>) -> String {
+    match env.get(name) {
+        Some(value) => value.clone(),
+        None => std::env::var(name).unwrap_or_default(),
+    }
+}
+
+fn expand(value: &str, lookup: &mut dyn FnMut(&str) -> String) -> String {
+    let mut interpolated = String::with_capacity(value.len());
+    let mut rest = value;
+
+    while let Some(start) = rest.find("${") {
+        let Some(length) = rest[start + 2..].find('}') else {
+            break;
+        };
+        let name = &rest[start + 2..start + 2 + length];
+
+        interpolated.push_str(&rest[..start]);
+        interpolated.push_str(&lookup(name));
+        rest = &rest[start + 2 + length + 1..];
+    }
+
+    interpolated.push_str(rest);
+    interpolated
+}
+
+fn parse_value(value: &str, env: &HashMap<String, String>) -> Result<String, String> {
+    if let Some(quoted) = value.strip_prefix('\'') {
+        let Some(end) = quoted.find('\'') else {
+            return Err("unterminated single quote".to_string());
+        };
+        return Ok(quoted[..end].to_string());
+    }
+
+    if let Some(quoted) = value.strip_prefix('"') {
+        // `\$` keeps a literal `$`, so text before it is interpolated first.
+        let mut parsed = String::new();
+        let mut unescaped = String::new();
+        let mut chars = quoted.chars();
+        loop {
+            match chars.next() {
+                Some('"') => {
+                    parsed.push_str(&interpolate(&unescaped, env));
+                    return Ok(parsed);
+                }
+                Some('\\') => match chars.next() {
+                    Some('n') => unescaped.push('\n'),
+                    Some('t') => unescaped.push('\t'),
+                    Some('$') => {
+                        parsed.push_str(&interpolate(&unescaped, env));
+                        parsed.push('$');
+                        unescaped.clear();
+                    }
+                    Some(c) => unescaped.push(c),
+                    None => return Err("unterminated double quote".to_string()),
+                },
+                Some(c) => unescaped.push(c),
+                None => return Err("unterminated double quote".to_string()),
+            }
+        }
+    }
+
+    let value = match value.find(" #") {
+        Some(comment) => value[..comment].trim_end(),
+        None => value,
+    };
+    Ok(interpolate(value, env))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn strings(pairs: &[(&str, &str)]) -> HashMap<String, String> {
+        pairs
+            .iter()
+            .map(|(key, value)| (key.to_string(), value.to_string()))
+            .collect()
+    }
+
+    fn load(name: &str, contents: &str) -> Result<HashMap<String, String>, Error> {
+        let path =
+            std::env::temp_dir().join(format!("shuru-dotenv-{}-{}", std::process::id(), name));
+        std::fs::write(&path, contents).unwrap();
+        let mut env = HashMap::new();
+        let result = load_env_file(&path, &mut env);
+        std::fs::remove_file(&path).unwrap();
+        result.map(|_| env)
+    }
+
+    #[test]
+    fn parses_quotes_escapes_and_export() {
+        let env = load(
+            "quotes",
+            concat!(
+                "# comment\n",
+                "\n",
+                "export NAME=world\n",
+                "PLAIN=hello ${NAME} # trailing comment\n",
+                "SINGLE='${NAME} # kept'\n",
+                "DOUBLE=\"line\\n${NAME}\\t\\\"quoted\\\" \\${NAME}\"\n",
+                "EMPTY=\n",
+            ),
+        )
+        .unwrap();
+
+        assert_eq!(env["NAME"], "world");
+        assert_eq!(env["PLAIN"], "hello world");
+        assert_eq!(env["SINGLE"], "${NAME} # kept");
+        assert_eq!(env["DOUBLE"], "line\nworld\t\"quoted\" ${NAME}");
+        assert_eq!(env["EMPTY"], "");
+    }
+
+    #[test]
+    fn reports_invalid_lines() {
+        for (name, contents) in [
+            ("no-equals", "JUST_A_NAME\n"),
+            ("bad-key", "NOT VALID=1\n"),
+            ("single", "KEY='open\n"),
+            ("double", "KEY=\"open\n"),
+        ] {
+            let error = load(name, contents).unwrap_err().to_string();
+            assert!(error.contains("line 1"), "{}: {}", name, error);
+        }
+    }
+
+    #[test]
+    fn skips_missing_files() {
+        let mut env = strings(&[("KEEP", "1")]);
+        load_env_file(Path::new("/nonexistent/shuru/.env.local"), &mut env).unwrap();
+        assert_eq!(env, strings(&[("KEEP", "1")]));
+    }
+
+    #[test]
+    fn interpolates_references_between_inline_variables() {
+        let inline = strings(&[
+            ("URL", "http://${HOST}:${PORT}"),
+            ("HOST", "${DOMAIN}"),
+            ("DOMAIN", "example.test"),
+        ]);
+        let env = strings(&[("PORT", "8080"), ("DOMAIN", "shadowed.test")]);
+
+        let resolved = interpolate_all(&inline, &env);
+        assert_eq!(resolved["URL"], "http://example.test:8080");
+        assert_eq!(resolved["HOST"], "example.test");
+    }
+
+    #[test]
+    fn self_references_use_the_variable_below() {
+        let inline = strings(&[("SHURU_TEST_PATH", "${SHURU_TEST_PATH}:bin")]);
+        let env = strings(&[("SHURU_TEST_PATH", "/usr")]);
+        assert_eq!(
+            interpolate_all(&inline, &env)["SHURU_TEST_PATH"],
+            "/usr:bin"
+        );
+    }
+
+    #[test]
+    fn finds_reference_cycles() {
+        let cycle = strings(&[("A", "${B}"), ("B", "${C}"), ("C", "${A}"), ("D", "${D}")]);
+        assert_eq!(
+            find_reference_cycle(&cycle).as_deref(),
+            Some("A -> B -> C -> A")
+        );
+
+        let acyclic = strings(&[("A", "${B}${B}"), ("B", "${B}-${C}"), ("C", "${UNKNOWN}")]);
+        assert_eq!(find_reference_cycle(&acyclic), None);
+    }
+}
@@ -0,0 +1,86 @@
+use std::time::Duration;
+
+/// Parses a human readable duration such as `500ms`, `30s`, `10m`, `2h` or
+/// a combination like `1h30m`.
+pub fn parse_duration(value: &str) -> Result<Duration, String> {
+    let value = value.trim();
+    if value.is_empty() {
+        return Err("duration is empty".to_string());
+    }
+
+    let mut total = Duration::ZERO;
+    let mut rest = value;
+
+    while !rest.is_empty() {
+        let digits = rest
+            .find(|c: char| !c.is_ascii_digit())
+            .unwrap_or(rest.len());
+        if digits == 0 {
+            return Err(format!("expected a number in '{}'", value));
+        }
+        let amount: u64 = rest[..digits]
+            .parse()
+            .map_err(|_| format!("number too large in '{}'", value))?;
+        rest = &rest[digits..];
+
+        let unit_length = rest
+            .find(|c: char| c.is_ascii_digit())
+            .unwrap_or(rest.len());
+        let seconds = |factor: u64| amount.checked_mul(factor).map(Duration::from_secs);
+        let unit = match &rest[..unit_length] {
+            "ms" => Some(Duration::from_millis(amount)),
+            "s" => Some(Duration::from_secs(amount)),
+            "m" => seconds(60),
+            "h" => seconds(60 * 60),
+            "" => return Err(format!("missing unit in '{}' (use ms, s, m or h)", value)),
+            other => {
+                return Err(format!(
+                    "unknown unit '{}' in '{}' (use ms, s, m or h)",
+                    other, value
+                ))
+            }
+        };
+        total = unit
+            .and_then(|unit| total.checked_add(unit))
+            .ok_or_else(|| format!("duration too large in '{}'", value))?;
+        rest = &rest[unit_length..];
+    }
+
+    Ok(total)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_single_units() {
+        assert_eq!(parse_duration("500ms"), Ok(Duration::from_millis(500)));
+        assert_eq!(parse_duration("30s"), Ok(Duration::from_secs(30)));
+        assert_eq!(parse_duration("10m"), Ok(Duration::from_secs(600)));
+        assert_eq!(parse_duration("2h"), Ok(Duration::from_secs(7200)));
+    }
+
+    #[test]
+    fn parses_combined_units() {
+        assert_eq!(parse_duration("1h30m"), Ok(Duration::from_secs(5400)));
+        assert_eq!(
+            parse_duration("1m30s500ms"),
+            Ok(Duration::from_millis(90_500))
+        );
+        assert_eq!(parse_duration(" 5s "), Ok(Duration::from_secs(5)));
+    }
+
+    #[test]
+    fn rejects_invalid_durations() {
+        for value in ["", "s", "10", "10d", "1.5s", "-5s", "5 s"] {
+            assert!(parse_duration(value).is_err(), "accepted '{}'", value);
+        }
+    }
+
+    #[test]
+    fn rejects_overflowing_durations() {
+        assert!(parse_duration("18446744073709551615h").is_err());
+        assert!(parse_duration("99999999999999999999s").is_err());
+    }
+}
@@ -1,34 +1,67 @@
 use flate2::read::GzDecoder;
-use shuru_core::error::Error;
+use shuru_core::error::{ConfigValidationError, Error};
 use std::fs::File;
 use std::path::Path;
 use tar::Archive;
 
+pub mod dotenv;
+pub mod duration;
 pub mod fuzzy_match;
+pub mod template;
 
 const EXIT_CONFIG_ERROR: i32 = 166;
 const EXIT_CONFIG_FILE_NOT_FOUND: i32 = 167;
 const EXIT_CONFIG_LOAD_ERROR: i32 = 168;
+const EXIT_DEPENDENCY_CYCLE: i32 = 169;
 
 const EXIT_COMMAND_ERROR: i32 = 170;
 const EXIT_COMMAND_NOT_FOUND: i32 = 171;
+const EXIT_TASK_PARAM_ERROR: i32 = 172;
+const EXIT_SHELL_NOT_FOUND: i32 = 173;
+// Matches the exit code of coreutils `timeout`.
+const EXIT_TASK_TIMEOUT: i32 = 124;
+const EXIT_SERVICE_NOT_READY: i32 = 174;
 
 const EXIT_VERSION_MANAGER_ERROR: i32 = 175;
 const EXIT_NO_DEFAULT_COMMAND_FOUND: i32 = 180;
 
-pub fn get_error_code(error: Error) -> i32 {
+pub fn get_error_code(error: &Error) -> i32 {
     match error {
-        Error::ConfigLoadError(_) => EXIT_CONFIG_LOAD_ERROR,
+        Error::ConfigLoadError(_) | Error::EnvFileError { .. } => EXIT_CONFIG_LOAD_ERROR,
+        Error::ConfigValidationError(ConfigValidationError::DependencyCycleError(_)) => {
+            EXIT_DEPENDENCY_CYCLE
+        }
         Error::ConfigValidationError(_) => EXIT_CONFIG_ERROR,
         Error::ConfigFileNotFound => EXIT_CONFIG_FILE_NOT_FOUND,
-        Error::CommandExecutionError(_) => EXIT_COMMAND_ERROR,
-        Error::CommandNotFound(_) => EXIT_COMMAND_NOT_FOUND,
+        Error::CommandExecutionError(_) | Error::NotAService(_) => EXIT_COMMAND_ERROR,
+        Error::CommandNotFound(_) | Error::PrivateTaskError(_) => EXIT_COMMAND_NOT_FOUND,
+        Error::TaskParamError(_) => EXIT_TASK_PARAM_ERROR,
+        Error::ShellNotFound(_) => EXIT_SHELL_NOT_FOUND,
+        Error::TaskTimedOut { .. } => EXIT_TASK_TIMEOUT,
+        Error::Interrupted(signal) => 128 + signal,
+        Error::ServiceNotReady { .. } | Error::ServiceExited { .. } => EXIT_SERVICE_NOT_READY,
         Error::VersionManagerError(_) => EXIT_VERSION_MANAGER_ERROR,
         Error::DefaultCommandNotFound => EXIT_NO_DEFAULT_COMMAND_FOUND,
+        Error::TaskFailed { code, .. }
+        | Error::TaskStepFailed { code, .. }
+        | Error::TaskHookFailed { code, .. } => *code,
         _ => 1,
     }
 }
 
+/// Converts a child's exit status into the code shuru should exit with,
+/// following the shell convention of 128 + signal for killed processes.
+pub fn exit_code(status: &std::process::ExitStatus) -> i32 {
+    #[cfg(unix)]
+    {
+        use std::os::unix::process::ExitStatusExt;
+        if let Some(signal) = status.signal() {
+            return 128 + signal;
+        }
+    }
+    status.code().unwrap_or(1)
+}
+
 pub fn os_type() -> &'static str {
     if cfg!(target_os = "macos") {
         "darwin"
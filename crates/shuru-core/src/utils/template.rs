@@ -0,0 +1,63 @@
+use std::collections::HashMap;
+
+/// Replaces every `{{name}}` placeholder that has an entry in `values`.
+/// Placeholders without a value are left untouched, so commands containing
+/// literal braces (e.g. `docker ps --format '{{.Names}}'`) keep working.
+pub fn render_template(template: &str, values: &HashMap<String, String>) -> String {
+    let mut rendered = String::with_capacity(template.len());
+    let mut rest = template;
+
+    while let Some(start) = rest.find("{{") {
+        let Some(length) = rest[start + 2..].find("}}") else {
+            break;
+        };
+        let end = start + 2 + length + 2;
+
+        rendered.push_str(&rest[..start]);
+        match values.get(&rest[start + 2..end - 2]) {
+            Some(value) => rendered.push_str(value),
+            None => rendered.push_str(&rest[start..end]),
+        }
+        rest = &rest[end..];
+    }
+
+    rendered.push_str(rest);
+    rendered
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn values(pairs: &[(&str, &str)]) -> HashMap<String, String> {
+        pairs
+            .iter()
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn replaces_placeholders_with_values() {
+        let values = values(&[("env", "staging"), ("region", "eu")]);
+        assert_eq!(
+            render_template("deploy {{env}} to {{region}}{{env}}", &values),
+            "deploy staging to eustaging"
+        );
+    }
+
+    #[test]
+    fn keeps_placeholders_without_values() {
+        let values = values(&[("name", "web")]);
+        assert_eq!(
+            render_template("docker ps --format '{{.Names}}' {{name}}", &values),
+            "docker ps --format '{{.Names}}' web"
+        );
+    }
+
+    #[test]
+    fn keeps_unterminated_placeholders() {
+        let values = values(&[("name", "web")]);
+        assert_eq!(render_template("echo {{name", &values), "echo {{name");
+        assert_eq!(render_template("{{name}} }}{{", &values), "web }}{{");
+    }
+}
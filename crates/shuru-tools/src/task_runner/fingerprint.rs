@@ -0,0 +1,153 @@
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+
+use sha2::{Digest, Sha256};
+use shuru_core::{config::Config, error::Error, task_config::TaskConfig};
+
+const FINGERPRINT_DIR: &str = ".shuru/fingerprints";
+
+/// Hash of everything that determines the result of a task with `sources`:
+/// the rendered commands and hooks, their work dir and env, the tool
+/// versions and the contents of every source file. Stored under
+/// `.shuru/fingerprints` in the project directory.
+pub struct Fingerprint {
+    digest: String,
+    dir: PathBuf,
+    /// `sources` patterns matching no files. A typo in one would otherwise
+    /// leave the task up to date forever.
+    unmatched: Vec<String>,
+}
+
+impl Fingerprint {
+    pub fn compute(
+        config: &Config,
+        task: &TaskConfig,
+        steps: &[String],
+        work_dir: &Path,
+        env: &HashMap<String, String>,
+        project_dir: &Path,
+    ) -> Result<Self, Error> {
+        let mut hasher = Sha256::new();
+
+        for step in steps {
+            hasher.update(step.as_bytes());
+            hasher.update([0]);
+        }
+        hasher.update(work_dir.to_string_lossy().as_bytes());
+        hasher.update([0]);
+
+        for (key, value) in env.iter().collect::<BTreeMap<_, _>>() {
+            hasher.update(format!("{}={}\0", key, value).as_bytes());
+        }
+
+        let mut versions: Vec<String> = config
+            .versions
+            .iter()
+            .map(|(command, version)| format!("{:?}={}", command, version))
+            .collect();
+        versions.sort();
+        for version in versions {
+            hasher.update(version.as_bytes());
+            hasher.update([0]);
+        }
+
+        let mut unmatched = Vec::new();
+        for pattern in &task.sources {
+            if Self::matching_paths(std::slice::from_ref(pattern), work_dir)?.is_empty() {
+                unmatched.push(pattern.clone());
+            }
+        }
+
+        let sources = Self::matching_paths(&task.sources, work_dir)?;
+        for path in sources.iter().filter(|path| path.is_file()) {
+            let contents = std::fs::read(path).map_err(|e| {
+                Error::CommandExecutionError(format!(
+                    "Description: Failed to read source file '{}': {}",
+                    path.display(),
+                    e
+                ))
+            })?;
+            hasher.update(path.to_string_lossy().as_bytes());
+            hasher.update([0]);
+            hasher.update(&contents);
+        }
+
+        let digest = hasher
+            .finalize()
+            .iter()
+            .map(|byte| format!("{:02x}", byte))
+            .collect();
+
+        Ok(Fingerprint {
+            digest,
+            dir: project_dir.join(FINGERPRINT_DIR),
+            unmatched,
+        })
+    }
+
+    pub fn unmatched_sources(&self) -> &[String] {
+        &self.unmatched
+    }
+
+    /// A task is up to date when every `sources` pattern matches a file, the
+    /// fingerprint of its last successful run matches and every `outputs`
+    /// pattern still matches at least one path.
+    pub fn is_up_to_date(&self, task_name: &str, task: &TaskConfig, work_dir: &Path) -> bool {
+        if !self.unmatched.is_empty() {
+            return false;
+        }
+
+        let stored = std::fs::read_to_string(self.path(task_name)).unwrap_or_default();
+        if stored.trim() != self.digest {
+            return false;
+        }
+
+        task.outputs.iter().all(|pattern| {
+            Self::matching_paths(std::slice::from_ref(pattern), work_dir)
+                .map(|paths| !paths.is_empty())
+                .unwrap_or(false)
+        })
+    }
+
+    pub fn save(&self, task_name: &str) -> Result<(), Error> {
+        let path = self.path(task_name);
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(path, &self.digest)?;
+        Ok(())
+    }
+
+    fn path(&self, task_name: &str) -> PathBuf {
+        self.dir.join(task_name)
+    }
+
+    fn matching_paths(patterns: &[String], work_dir: &Path) -> Result<Vec<PathBuf>, Error> {
+        let mut matches = Vec::new();
+
+        for pattern in patterns {
+            let full_pattern = resolve_glob(work_dir, pattern);
+            let paths = glob::glob(&full_pattern.to_string_lossy()).map_err(|e| {
+                Error::CommandExecutionError(format!(
+                    "Description: Invalid glob pattern '{}': {}",
+                    pattern, e
+                ))
+            })?;
+
+            matches.extend(paths.flatten());
+        }
+
+        matches.sort();
+        matches.dedup();
+        Ok(matches)
+    }
+}
+
+/// Joins a task glob pattern onto its work dir. `glob` matches nothing for a
+/// trailing `**`, so `dist/**` is treated as everything below `dist`.
+pub fn resolve_glob(work_dir: &Path, pattern: &str) -> PathBuf {
+    match pattern.ends_with("**") {
+        true => work_dir.join(pattern).join("*"),
+        false => work_dir.join(pattern),
+    }
+}
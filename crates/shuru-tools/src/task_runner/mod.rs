@@ -1,6 +1,16 @@
 pub use shuru_core::task_config::TaskConfig;
 
+pub mod process;
 pub mod shell;
 
+mod fingerprint;
+mod output;
+mod params;
 mod runner;
-pub use runner::TaskRunner;
+mod scheduler;
+mod service;
+mod supervisor;
+mod watcher;
+pub use output::OutputMode;
+pub use params::TaskArgs;
+pub use runner::{RunOptions, TaskRunner};
@@ -0,0 +1,190 @@
+use std::io::{BufRead, BufReader, Read, Write};
+use std::process::{Child, Command, Stdio};
+use std::str::FromStr;
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use regex::Regex;
+
+const PREFIX_COLORS: [u8; 6] = [36, 33, 32, 35, 34, 31];
+/// How long the output of a command is still forwarded after it exited.
+const DRAIN_TIMEOUT: Duration = Duration::from_millis(500);
+const DRAIN_POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+/// How the output of task commands reaches the terminal.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum OutputMode {
+    /// Commands write straight to the inherited stdout and stderr.
+    #[default]
+    Interleaved,
+    /// Every line is printed as soon as it arrives, prefixed with the task name.
+    Prefixed,
+    /// Lines are buffered and printed in one block once the task finishes.
+    Grouped,
+}
+
+impl FromStr for OutputMode {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "interleaved" => Ok(OutputMode::Interleaved),
+            "prefixed" => Ok(OutputMode::Prefixed),
+            "grouped" => Ok(OutputMode::Grouped),
+            _ => Err(format!(
+                "unknown output mode '{}' (expected interleaved, prefixed or grouped)",
+                value
+            )),
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+enum Stream {
+    Stdout,
+    Stderr,
+}
+
+/// Output of a single task run. For [`OutputMode::Grouped`] the captured
+/// lines are printed when this is dropped, so a block is printed even when
+/// the task fails.
+pub struct TaskOutput {
+    mode: OutputMode,
+    prefix: String,
+    timestamps: bool,
+    buffer: Arc<Mutex<Vec<(Stream, String)>>>,
+    /// Pattern a printed line is matched against, notifying the sender once.
+    watched: Option<(Regex, Sender<()>)>,
+}
+
+impl TaskOutput {
+    pub fn new(task_name: &str, mode: OutputMode, timestamps: bool) -> Self {
+        let color =
+            PREFIX_COLORS[task_name.bytes().map(usize::from).sum::<usize>() % PREFIX_COLORS.len()];
+        TaskOutput {
+            mode,
+            prefix: format!("\x1b[{}m[{}]\x1b[0m", color, task_name),
+            timestamps,
+            buffer: Arc::default(),
+            watched: None,
+        }
+    }
+
+    /// Notifies the returned receiver once a line printed by the task matches
+    /// `pattern`. Output is then captured even when it is interleaved.
+    pub fn watch_for(&mut self, pattern: Regex) -> Receiver<()> {
+        let (sender, receiver) = mpsc::channel();
+        self.watched = Some((pattern, sender));
+        receiver
+    }
+
+    /// Pipes stdout and stderr of `command` unless output is interleaved and
+    /// no pattern is watched for.
+    pub fn configure(&self, command: &mut Command) {
+        if self.mode != OutputMode::Interleaved || self.watched.is_some() {
+            command.stdout(Stdio::piped()).stderr(Stdio::piped());
+        }
+    }
+
+    /// Starts forwarding the piped output of `child`. The returned handles
+    /// finish once the child closed both streams.
+    pub fn capture(&self, child: &mut Child) -> Vec<JoinHandle<()>> {
+        let mut handles = Vec::new();
+        if let Some(stdout) = child.stdout.take() {
+            handles.push(self.forward(stdout, Stream::Stdout));
+        }
+        if let Some(stderr) = child.stderr.take() {
+            handles.push(self.forward(stderr, Stream::Stderr));
+        }
+        handles
+    }
+
+    /// Waits for the handles returned by [`TaskOutput::capture`] once the
+    /// command exited. A background process it left behind can hold the
+    /// pipes open indefinitely, so readers still running after a short
+    /// while are left to finish on their own.
+    pub fn join(readers: Vec<JoinHandle<()>>) {
+        let deadline = Instant::now() + DRAIN_TIMEOUT;
+        while readers.iter().any(|reader| !reader.is_finished()) && Instant::now() < deadline {
+            std::thread::sleep(DRAIN_POLL_INTERVAL);
+        }
+        for reader in readers.into_iter().filter(|reader| reader.is_finished()) {
+            let _ = reader.join();
+        }
+    }
+
+    fn forward<R: Read + Send + 'static>(&self, reader: R, stream: Stream) -> JoinHandle<()> {
+        let mode = self.mode;
+        let prefix = self.prefix.clone();
+        let timestamps = self.timestamps;
+        let buffer = Arc::clone(&self.buffer);
+        let mut watched = self.watched.clone();
+
+        std::thread::spawn(move || {
+            let mut reader = BufReader::new(reader);
+            let mut line = Vec::new();
+            while matches!(reader.read_until(b'\n', &mut line), Ok(length) if length > 0) {
+                let text = String::from_utf8_lossy(&line);
+                let text = text.trim_end_matches(['\n', '\r']);
+                if let Some((pattern, sender)) = &watched {
+                    if pattern.is_match(text) {
+                        let _ = sender.send(());
+                        watched = None;
+                    }
+                }
+                let formatted = match (mode, timestamps) {
+                    (OutputMode::Interleaved, _) => text.to_string(),
+                    (_, true) => format!("{} \x1b[90m{}\x1b[0m {}", prefix, timestamp(), text),
+                    (_, false) => format!("{} {}", prefix, text),
+                };
+                match mode {
+                    OutputMode::Grouped => buffer.lock().unwrap().push((stream, formatted)),
+                    _ => write_line(stream, &formatted),
+                }
+                line.clear();
+            }
+        })
+    }
+}
+
+impl Drop for TaskOutput {
+    fn drop(&mut self) {
+        let lines = std::mem::take(&mut *self.buffer.lock().unwrap());
+        if lines.is_empty() {
+            return;
+        }
+
+        let mut stdout = std::io::stdout().lock();
+        let mut stderr = std::io::stderr().lock();
+        for (stream, line) in lines {
+            let _ = match stream {
+                Stream::Stdout => writeln!(stdout, "{}", line),
+                Stream::Stderr => writeln!(stderr, "{}", line),
+            };
+        }
+    }
+}
+
+fn write_line(stream: Stream, line: &str) {
+    let _ = match stream {
+        Stream::Stdout => writeln!(std::io::stdout().lock(), "{}", line),
+        Stream::Stderr => writeln!(std::io::stderr().lock(), "{}", line),
+    };
+}
+
+/// Current UTC time of day as `HH:MM:SS.mmm`.
+fn timestamp() -> String {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default();
+    let seconds = now.as_secs() % 86_400;
+    format!(
+        "{:02}:{:02}:{:02}.{:03}",
+        seconds / 3600,
+        seconds / 60 % 60,
+        seconds % 60,
+        now.subsec_millis()
+    )
+}
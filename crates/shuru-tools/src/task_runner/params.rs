@@ -0,0 +1,220 @@
+use std::collections::HashMap;
+
+use shuru_core::{
+    error::Error,
+    task_config::{ParamType, TaskConfig, TaskParam},
+};
+
+/// Arguments given after the task name on the command line.
+#[derive(Debug, Default, Clone)]
+pub struct TaskArgs {
+    /// `--name=value` parameters declared in the task's `params`.
+    pub params: Vec<String>,
+    /// Everything after `--`, forwarded to the task's command.
+    pub extra: Vec<String>,
+}
+
+impl TaskArgs {
+    /// Splits the raw arguments at the first `--` into parameters and extra
+    /// command arguments.
+    pub fn new(mut params: Vec<String>, mut extra: Vec<String>) -> Self {
+        if let Some(position) = params.iter().position(|arg| arg == "--") {
+            let mut trailing = params.split_off(position + 1);
+            params.pop();
+            trailing.append(&mut extra);
+            extra = trailing;
+        }
+        TaskArgs { params, extra }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.params.is_empty() && self.extra.is_empty()
+    }
+
+    /// Resolves the value of every parameter declared by `task`, applying
+    /// defaults and checking types, enum values and required parameters.
+    pub fn resolve_params(
+        &self,
+        task_name: &str,
+        task: &TaskConfig,
+    ) -> Result<HashMap<String, String>, Error> {
+        let mut values = HashMap::new();
+        let mut tokens = self.params.iter();
+
+        while let Some(token) = tokens.next() {
+            let Some(param_arg) = token.strip_prefix("--") else {
+                return Err(Error::TaskParamError(format!(
+                    "Description: Unexpected argument '{}' for task '{}'\n    Hint: Use `--` to pass extra arguments to the command.",
+                    token, task_name
+                )));
+            };
+
+            let (name, value) = match param_arg.split_once('=') {
+                Some((name, value)) => (name, Some(value.to_string())),
+                None => (param_arg, None),
+            };
+
+            let Some(param) = task.params.get(name) else {
+                return Err(Error::TaskParamError(format!(
+                    "Description: Unknown parameter '--{}' for task '{}'\n    Hint: {}",
+                    name,
+                    task_name,
+                    Self::available_params(task)
+                )));
+            };
+
+            let value = match (value, param.kind) {
+                (Some(value), _) => value,
+                (None, ParamType::Bool) => "true".to_string(),
+                (None, _) => tokens.next().cloned().ok_or_else(|| {
+                    Error::TaskParamError(format!(
+                        "Description: Missing value for parameter '--{}' of task '{}'\n    Hint: {}",
+                        name,
+                        task_name,
+                        param.usage(name)
+                    ))
+                })?,
+            };
+
+            Self::check_value(task_name, name, param, &value)?;
+            values.insert(name.to_string(), value);
+        }
+
+        for (name, param) in &task.params {
+            if values.contains_key(name) {
+                continue;
+            }
+
+            let value = match (&param.default, param.kind) {
+                (Some(default), _) => default.to_string(),
+                (None, _) if param.is_required() => {
+                    return Err(Error::TaskParamError(format!(
+                        "Description: Missing required parameter '--{}' for task '{}'\n    Hint: {}",
+                        name,
+                        task_name,
+                        Self::valid_values(name, param)
+                    )));
+                }
+                (None, ParamType::Bool) => "false".to_string(),
+                (None, _) => String::new(),
+            };
+            values.insert(name.clone(), value);
+        }
+
+        Ok(values)
+    }
+
+    fn check_value(
+        task_name: &str,
+        name: &str,
+        param: &TaskParam,
+        value: &str,
+    ) -> Result<(), Error> {
+        let valid = match param.kind {
+            ParamType::String => true,
+            ParamType::Bool => value == "true" || value == "false",
+            ParamType::Enum => param.values.iter().any(|allowed| allowed == value),
+        };
+
+        if !valid {
+            return Err(Error::TaskParamError(format!(
+                "Description: Invalid value '{}' for parameter '--{}' of task '{}'\n    Hint: {}",
+                value,
+                name,
+                task_name,
+                Self::valid_values(name, param)
+            )));
+        }
+        Ok(())
+    }
+
+    fn valid_values(name: &str, param: &TaskParam) -> String {
+        match param.kind {
+            ParamType::String => format!("Usage: {}", param.usage(name)),
+            ParamType::Bool => "Valid values: true, false".to_string(),
+            ParamType::Enum => format!("Valid values: {}", param.values.join(", ")),
+        }
+    }
+
+    fn available_params(task: &TaskConfig) -> String {
+        if task.params.is_empty() {
+            return "This task takes no parameters. Use `--` to pass extra arguments to the command."
+                .to_string();
+        }
+
+        let mut usages: Vec<String> = task
+            .params
+            .iter()
+            .map(|(name, param)| param.usage(name))
+            .collect();
+        usages.sort();
+        format!("Available parameters:\n      {}", usages.join("\n      "))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn task() -> TaskConfig {
+        toml::from_str(
+            r#"
+            command = "deploy {{env}}"
+            [params.env]
+            type = "enum"
+            values = ["staging", "production"]
+            required = true
+            [params.dry-run]
+            type = "bool"
+            [params.region]
+            default = "eu"
+            "#,
+        )
+        .unwrap()
+    }
+
+    fn resolve(params: &[&str]) -> Result<HashMap<String, String>, Error> {
+        let args = TaskArgs::new(
+            params.iter().map(|param| param.to_string()).collect(),
+            Vec::new(),
+        );
+        args.resolve_params("deploy", &task())
+    }
+
+    #[test]
+    fn splits_extra_arguments_at_double_dash() {
+        let args = TaskArgs::new(
+            vec!["--env=staging".into(), "--".into(), "-v".into()],
+            vec!["--dry-run".into()],
+        );
+        assert_eq!(args.params, ["--env=staging"]);
+        assert_eq!(args.extra, ["-v", "--dry-run"]);
+    }
+
+    #[test]
+    fn resolves_values_and_defaults() {
+        let values = resolve(&["--env=staging", "--dry-run"]).unwrap();
+        assert_eq!(values["env"], "staging");
+        assert_eq!(values["dry-run"], "true");
+        assert_eq!(values["region"], "eu");
+
+        let values = resolve(&["--env", "production", "--region", "us"]).unwrap();
+        assert_eq!(values["env"], "production");
+        assert_eq!(values["dry-run"], "false");
+        assert_eq!(values["region"], "us");
+    }
+
+    #[test]
+    fn rejects_invalid_values() {
+        assert!(resolve(&["--env=qa"]).is_err());
+        assert!(resolve(&["--env=staging", "--dry-run=yes"]).is_err());
+    }
+
+    #[test]
+    fn rejects_missing_and_unknown_parameters() {
+        assert!(resolve(&[]).is_err());
+        assert!(resolve(&["--env"]).is_err());
+        assert!(resolve(&["--env=staging", "--verbose"]).is_err());
+        assert!(resolve(&["--env=staging", "positional"]).is_err());
+    }
+}
@@ -0,0 +1,539 @@
+use std::io::{self, IsTerminal};
+use std::process::{Child, Command, ExitStatus, Stdio};
+use std::sync::{
+    atomic::{AtomicBool, AtomicUsize, Ordering},
+    Arc, OnceLock,
+};
+use std::time::{Duration, Instant};
+
+const POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// Signals shuru forwards to the process groups of running tasks.
+#[cfg(unix)]
+const FORWARDED_SIGNALS: [i32; 3] = [libc::SIGINT, libc::SIGTERM, libc::SIGHUP];
+#[cfg(not(unix))]
+const FORWARDED_SIGNALS: [i32; 2] = [signal_hook::consts::SIGINT, signal_hook::consts::SIGTERM];
+
+/// Spawns `command` as the leader of a new process group, so the task and
+/// every process it starts can be signalled together.
+pub fn spawn_in_group(command: &mut Command) -> io::Result<Child> {
+    #[cfg(unix)]
+    {
+        use std::os::unix::process::CommandExt;
+        command.process_group(0);
+    }
+    command.spawn()
+}
+
+/// Set while a task's process group owns the terminal.
+static FOREGROUND_TAKEN: AtomicBool = AtomicBool::new(false);
+
+/// Hands the terminal to the process group of a task while it runs, so the
+/// task can read from it and receives Ctrl-C directly, and gives it back to
+/// shuru when dropped. Only one task holds the terminal at a time; nothing
+/// happens when stdin is not a terminal or shuru is not in the foreground.
+pub struct Foreground {
+    #[cfg(unix)]
+    shuru_group: Option<libc::pid_t>,
+}
+
+impl Foreground {
+    /// Reserves the terminal for the task about to be spawned, when stdin is
+    /// a terminal shuru owns and no other task holds it.
+    pub fn claim() -> Self {
+        #[cfg(unix)]
+        {
+            // SAFETY: these calls only query the process group and terminal.
+            let shuru_group = unsafe { libc::getpgrp() };
+            let owns_terminal = stdin_is_terminal()
+                && unsafe { libc::tcgetpgrp(libc::STDIN_FILENO) } == shuru_group;
+            if !owns_terminal
+                || FOREGROUND_TAKEN
+                    .compare_exchange(false, true, Ordering::AcqRel, Ordering::Relaxed)
+                    .is_err()
+            {
+                return Foreground { shuru_group: None };
+            }
+            Foreground {
+                shuru_group: Some(shuru_group),
+            }
+        }
+
+        #[cfg(not(unix))]
+        {
+            Foreground {}
+        }
+    }
+
+    /// Hands a claimed terminal to the process group led by `pid`.
+    pub fn hand_to(&mut self, pid: Option<u32>) {
+        #[cfg(unix)]
+        {
+            if self.shuru_group.is_none() {
+                return;
+            }
+            match pid {
+                Some(pid) if set_foreground_group(pid as libc::pid_t) => {
+                    // The task may have read from the terminal before it was
+                    // handed over and been stopped by SIGTTIN.
+                    signal_group(pid, libc::SIGCONT);
+                }
+                _ => {
+                    self.shuru_group = None;
+                    FOREGROUND_TAKEN.store(false, Ordering::Release);
+                }
+            }
+        }
+
+        #[cfg(not(unix))]
+        {
+            let _ = pid;
+        }
+    }
+
+    /// Claims the terminal and hands it to the process group led by `pid`.
+    pub fn give(pid: Option<u32>) -> Self {
+        let mut foreground = Self::claim();
+        foreground.hand_to(pid);
+        foreground
+    }
+
+    /// Whether the task's group got the terminal.
+    pub fn is_held(&self) -> bool {
+        #[cfg(unix)]
+        {
+            self.shuru_group.is_some()
+        }
+
+        #[cfg(not(unix))]
+        {
+            false
+        }
+    }
+}
+
+impl Drop for Foreground {
+    fn drop(&mut self) {
+        #[cfg(unix)]
+        if let Some(group) = self.shuru_group.take() {
+            set_foreground_group(group);
+            FOREGROUND_TAKEN.store(false, Ordering::Release);
+        }
+    }
+}
+
+pub fn stdin_is_terminal() -> bool {
+    io::stdin().is_terminal()
+}
+
+/// Keeps a task that does not hold the terminal from being stopped by it,
+/// which would leave it stopped for good: its stdin is closed and SIGTTIN
+/// and SIGTTOU are ignored, so using the terminal fails or goes through.
+pub fn keep_off_terminal(command: &mut Command) {
+    command.stdin(Stdio::null());
+    #[cfg(unix)]
+    {
+        use std::os::unix::process::CommandExt;
+        // SAFETY: signal is async-signal-safe. Ignored signals stay ignored
+        // across exec and in every process the task starts.
+        unsafe {
+            command.pre_exec(|| {
+                libc::signal(libc::SIGTTIN, libc::SIG_IGN);
+                libc::signal(libc::SIGTTOU, libc::SIG_IGN);
+                Ok(())
+            });
+        }
+    }
+}
+
+#[cfg(unix)]
+fn set_foreground_group(group: libc::pid_t) -> bool {
+    // SAFETY: SIGTTOU is ignored only around tcsetpgrp, which would otherwise
+    // stop shuru when it takes the terminal back from a background group.
+    unsafe {
+        let previous = libc::signal(libc::SIGTTOU, libc::SIG_IGN);
+        let result = libc::tcsetpgrp(libc::STDIN_FILENO, group);
+        libc::signal(libc::SIGTTOU, previous);
+        result == 0
+    }
+}
+
+/// The signal that terminated `status` when it came from the terminal
+/// (Ctrl-C or Ctrl-Backslash), which only reaches a task holding the [`Foreground`].
+pub fn terminal_signal(status: &ExitStatus) -> Option<i32> {
+    #[cfg(unix)]
+    {
+        use std::os::unix::process::ExitStatusExt;
+        status
+            .signal()
+            .filter(|signal| [libc::SIGINT, libc::SIGQUIT].contains(signal))
+    }
+
+    #[cfg(not(unix))]
+    {
+        let _ = status;
+        None
+    }
+}
+
+/// How waiting for a child ended.
+pub enum Wait {
+    Exited(ExitStatus),
+    TimedOut,
+    /// Shuru received the contained signal.
+    Interrupted(i32),
+    /// The caller no longer needs the child, e.g. `--watch` restarts it.
+    Cancelled,
+    /// The child was stopped by the contained SIGTTIN or SIGTTOU for using
+    /// the terminal without holding it. Nothing would ever resume it.
+    Stopped(i32),
+}
+
+/// Waits for `child` to exit, giving up once `deadline` has passed, a
+/// signal was recorded in `interrupted`, `cancelled` was set or the child was
+/// stopped by the terminal while it is still running.
+pub fn wait_for(
+    child: &mut Child,
+    deadline: Option<Instant>,
+    interrupted: Option<&AtomicUsize>,
+    cancelled: Option<&AtomicBool>,
+) -> io::Result<Wait> {
+    loop {
+        if let Some(status) = child.try_wait()? {
+            return Ok(Wait::Exited(status));
+        }
+        if let Some(signal) = terminal_stop(child) {
+            return Ok(Wait::Stopped(signal));
+        }
+        if let Some(signal) = interrupted.map(|flag| flag.load(Ordering::Relaxed)) {
+            if signal != 0 {
+                return Ok(Wait::Interrupted(signal as i32));
+            }
+        }
+        if cancelled.is_some_and(|flag| flag.load(Ordering::Relaxed)) {
+            return Ok(Wait::Cancelled);
+        }
+        let mut interval = POLL_INTERVAL;
+        if let Some(deadline) = deadline {
+            let now = Instant::now();
+            if now >= deadline {
+                return Ok(Wait::TimedOut);
+            }
+            interval = interval.min(deadline - now);
+        }
+        std::thread::sleep(interval);
+    }
+}
+
+/// The signal that stopped `child` when it was SIGTTIN or SIGTTOU, without
+/// reaping it.
+#[cfg(target_os = "linux")]
+fn terminal_stop(child: &Child) -> Option<i32> {
+    // SAFETY: waitid only fills `info`; WNOWAIT leaves the child waitable.
+    unsafe {
+        let mut info: libc::siginfo_t = std::mem::zeroed();
+        let result = libc::waitid(
+            libc::P_PID,
+            child.id() as libc::id_t,
+            &mut info,
+            libc::WSTOPPED | libc::WNOHANG | libc::WNOWAIT,
+        );
+        if result != 0 || info.si_signo != libc::SIGCHLD {
+            return None;
+        }
+        let signal = info.si_status();
+        [libc::SIGTTIN, libc::SIGTTOU]
+            .contains(&signal)
+            .then_some(signal)
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+fn terminal_stop(_child: &Child) -> Option<i32> {
+    None
+}
+
+/// Stops the process group led by `child` with SIGTERM, escalating to
+/// SIGKILL when it is still running after `grace`.
+pub fn terminate_group(child: &mut Child, grace: Duration) -> io::Result<ExitStatus> {
+    #[cfg(unix)]
+    let signal = libc::SIGTERM;
+    #[cfg(not(unix))]
+    let signal = signal_hook::consts::SIGTERM;
+
+    forward_signal(child, signal, grace)
+}
+
+/// Sends `signal` to the process group led by `child` and waits for the whole
+/// group to exit. Processes outliving the leader, such as background jobs that
+/// ignore SIGINT, get SIGTERM; anything still running after `grace` is killed.
+pub fn forward_signal(child: &mut Child, signal: i32, grace: Duration) -> io::Result<ExitStatus> {
+    #[cfg(unix)]
+    {
+        let mut escalation = Escalation::start(child.id(), signal, grace);
+        while !escalation.is_done(child.try_wait()?) {
+            std::thread::sleep(POLL_INTERVAL);
+        }
+        match escalation.status {
+            Some(status) => Ok(status),
+            None => child.wait(),
+        }
+    }
+
+    #[cfg(not(unix))]
+    {
+        let _ = (signal, grace);
+        child.kill()?;
+        child.wait()
+    }
+}
+
+/// Async counterpart of waiting for a child and [`forward_signal`]: waits for
+/// `child`, forwarding SIGINT, SIGTERM or SIGHUP received by shuru to its
+/// process group, which is cleaned up the same way.
+pub async fn wait_forwarding_signals(
+    child: &mut tokio::process::Child,
+    grace: Duration,
+) -> io::Result<ExitStatus> {
+    #[cfg(unix)]
+    {
+        let interrupted = InterruptFlag::register()?;
+        let signal = loop {
+            tokio::select! {
+                status = child.wait() => return status,
+                _ = tokio::time::sleep(POLL_INTERVAL) => {}
+            }
+            let signal = interrupted.flag().load(Ordering::Relaxed) as i32;
+            if signal != 0 {
+                break signal;
+            }
+        };
+
+        let Some(pid) = child.id() else {
+            return child.wait().await;
+        };
+        let mut escalation = Escalation::start(pid, signal, grace);
+        while !escalation.is_done(child.try_wait()?) {
+            tokio::time::sleep(POLL_INTERVAL).await;
+        }
+        match escalation.status {
+            Some(status) => Ok(status),
+            None => child.wait().await,
+        }
+    }
+
+    #[cfg(not(unix))]
+    {
+        let _ = grace;
+        child.wait().await
+    }
+}
+
+/// Cleanup of a process group after a signal was forwarded to it, advanced
+/// by polling its leader until [`Escalation::is_done`].
+#[cfg(unix)]
+struct Escalation {
+    pid: u32,
+    deadline: Instant,
+    swept: bool,
+    status: Option<ExitStatus>,
+}
+
+#[cfg(unix)]
+impl Escalation {
+    fn start(pid: u32, signal: i32, grace: Duration) -> Self {
+        signal_group(pid, signal);
+        // Stopped processes only act on the signal once they are continued.
+        signal_group(pid, libc::SIGCONT);
+        Escalation {
+            pid,
+            deadline: Instant::now() + grace,
+            swept: false,
+            status: None,
+        }
+    }
+
+    /// Takes the latest status of the leader and tells whether the group is
+    /// gone, or was killed because `grace` passed.
+    fn is_done(&mut self, status: Option<ExitStatus>) -> bool {
+        if self.status.is_none() {
+            self.status = status;
+        }
+        if self.status.is_some() {
+            if !group_alive(self.pid) {
+                return true;
+            }
+            if !self.swept {
+                signal_group(self.pid, libc::SIGTERM);
+                self.swept = true;
+            }
+        }
+        if Instant::now() >= self.deadline {
+            signal_group(self.pid, libc::SIGKILL);
+            return true;
+        }
+        false
+    }
+}
+
+/// When the process with `pid` started, as an opaque value that tells it
+/// apart from a later process reusing the pid, or None when there is none.
+pub fn start_time(pid: u32) -> Option<String> {
+    #[cfg(target_os = "linux")]
+    {
+        // Field 22 of the stat line, counted after the parenthesised name,
+        // which may itself contain spaces.
+        let stat = std::fs::read_to_string(format!("/proc/{}/stat", pid)).ok()?;
+        let fields = stat.rsplit_once(')')?.1;
+        fields.split_whitespace().nth(19).map(str::to_string)
+    }
+
+    #[cfg(all(unix, not(target_os = "linux")))]
+    {
+        let output = Command::new("ps")
+            .args(["-o", "lstart=", "-p", &pid.to_string()])
+            .stdin(std::process::Stdio::null())
+            .stderr(std::process::Stdio::null())
+            .output()
+            .ok()?;
+        let started = String::from_utf8_lossy(&output.stdout).trim().to_string();
+        (output.status.success() && !started.is_empty()).then_some(started)
+    }
+
+    #[cfg(not(unix))]
+    {
+        let _ = pid;
+        None
+    }
+}
+
+/// Whether the process group led by `pid`, whose leader started at
+/// `started`, is still running, for groups shuru did not spawn in this run.
+/// A leader with another start time is an unrelated process that reused the
+/// pid. Once the leader exited, its pid is not reused while any other member
+/// of its group is alive, so the group can be checked directly.
+pub fn detached_group_running(pid: u32, started: &str) -> bool {
+    #[cfg(unix)]
+    {
+        match start_time(pid) {
+            Some(leader_started) => leader_started == started,
+            None => group_alive(pid),
+        }
+    }
+
+    #[cfg(not(unix))]
+    {
+        let _ = (pid, started);
+        false
+    }
+}
+
+/// Stops the process group led by `pid`, which is not a child of shuru, with
+/// SIGTERM, escalating to SIGKILL when it is still running after `grace`.
+pub fn terminate_detached_group(pid: u32, grace: Duration) -> io::Result<()> {
+    #[cfg(unix)]
+    {
+        signal_group(pid, libc::SIGTERM);
+
+        let deadline = Instant::now() + grace;
+        while group_alive(pid) {
+            if Instant::now() >= deadline {
+                signal_group(pid, libc::SIGKILL);
+                break;
+            }
+            std::thread::sleep(POLL_INTERVAL);
+        }
+        Ok(())
+    }
+
+    #[cfg(not(unix))]
+    {
+        let _ = (pid, grace);
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "stopping detached services is only supported on Unix",
+        ))
+    }
+}
+
+#[cfg(unix)]
+fn signal_group(pid: u32, signal: i32) {
+    // SAFETY: killpg only sends a signal; a group that already exited is
+    // reported through the return value, which is irrelevant here.
+    unsafe {
+        libc::killpg(pid as libc::pid_t, signal);
+    }
+}
+
+/// Whether any process of the group led by `pid` is still running.
+#[cfg(unix)]
+fn group_alive(pid: u32) -> bool {
+    // SAFETY: signal 0 only checks that the group exists.
+    unsafe { libc::killpg(pid as libc::pid_t, 0) == 0 }
+}
+
+/// Number of live [`InterruptFlag`]s. While there are none, the signals
+/// they record get their default action back.
+static INTERRUPT_FLAGS: AtomicUsize = AtomicUsize::new(0);
+static DEFAULT_ACTIONS: OnceLock<Result<(), String>> = OnceLock::new();
+
+/// Holds the last of SIGINT, SIGTERM or SIGHUP shuru received, or 0. While
+/// it is alive, these signals no longer terminate shuru itself, so it can
+/// forward them and clean up; once dropped, they do again.
+pub struct InterruptFlag {
+    flag: Arc<AtomicUsize>,
+    ids: Vec<signal_hook::SigId>,
+}
+
+impl InterruptFlag {
+    pub fn register() -> io::Result<Self> {
+        register_default_actions()?;
+
+        let flag = Arc::new(AtomicUsize::new(0));
+        let mut interrupt = InterruptFlag {
+            flag: Arc::clone(&flag),
+            ids: Vec::new(),
+        };
+        INTERRUPT_FLAGS.fetch_add(1, Ordering::SeqCst);
+        for signal in FORWARDED_SIGNALS {
+            let id = signal_hook::flag::register_usize(signal, Arc::clone(&flag), signal as usize)?;
+            interrupt.ids.push(id);
+        }
+        Ok(interrupt)
+    }
+
+    pub fn flag(&self) -> &Arc<AtomicUsize> {
+        &self.flag
+    }
+}
+
+impl Drop for InterruptFlag {
+    fn drop(&mut self) {
+        for id in self.ids.drain(..) {
+            signal_hook::low_level::unregister(id);
+        }
+        INTERRUPT_FLAGS.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+/// Signal-hook keeps its handler installed once a signal was registered, so
+/// it performs the default action itself while no [`InterruptFlag`] is alive.
+fn register_default_actions() -> io::Result<()> {
+    let registered = DEFAULT_ACTIONS.get_or_init(|| {
+        #[cfg(unix)]
+        for signal in FORWARDED_SIGNALS {
+            // SAFETY: the action only reads an atomic and calls
+            // emulate_default_handler, both async-signal-safe.
+            unsafe {
+                signal_hook::low_level::register(signal, move || {
+                    if INTERRUPT_FLAGS.load(Ordering::SeqCst) == 0 {
+                        let _ = signal_hook::low_level::emulate_default_handler(signal);
+                    }
+                })
+            }
+            .map_err(|e| e.to_string())?;
+        }
+        Ok(())
+    });
+    registered.clone().map_err(io::Error::other)
+}
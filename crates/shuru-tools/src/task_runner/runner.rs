@@ -1,25 +1,167 @@
-use std::path::PathBuf;
-use std::process::ExitStatus;
+use std::collections::HashMap;
+use std::num::NonZeroUsize;
+use std::path::{Path, PathBuf};
+use std::process::{ExitStatus, Stdio};
+use std::sync::{
+    atomic::{AtomicBool, AtomicUsize, Ordering},
+    Arc, Mutex, OnceLock,
+};
+use std::time::{Duration, Instant};
 
-use shuru_core::{config::Config, error::Error};
+use shuru_core::{
+    config::Config,
+    error::ConfigValidationError,
+    error::Error,
+    task_config::{
+        Platform, ReadyProbe, RetryBackoff, ARGS_PARAM, ARGS_PLACEHOLDER, DEFAULT_READY_TIMEOUT,
+    },
+    utils::{
+        dotenv::{interpolate_all, load_env_file},
+        template::render_template,
+    },
+};
 
 use shuru_tools::{
-    task_runner::{shell::Shell, TaskConfig},
+    task_runner::{
+        fingerprint::Fingerprint,
+        output::{OutputMode, TaskOutput},
+        params::TaskArgs,
+        process::{self, InterruptFlag, Wait},
+        scheduler::{Scheduler, TaskGraph, TaskOutcome},
+        service::{self, Readiness, Service},
+        shell::Shell,
+        supervisor::{self, ServiceState},
+        watcher::FileWatcher,
+        TaskConfig,
+    },
     version_manager::EnvPathBuilder,
 };
 
+pub struct RunOptions {
+    /// Maximum number of tasks running at the same time.
+    pub jobs: NonZeroUsize,
+    /// Run tasks with `sources` even when they are up to date.
+    pub force: bool,
+    /// Re-run the requested task whenever its watched files change.
+    pub watch: bool,
+    /// How the output of concurrently running tasks is presented.
+    pub output: OutputMode,
+    /// Prefix every captured output line with the time it was printed.
+    pub timestamps: bool,
+    /// Run several requested tasks at the same time instead of in order.
+    pub parallel: bool,
+    /// Keep running tasks that do not depend on a failed one.
+    pub keep_going: bool,
+    /// Leave services running in the background after the run, supervised
+    /// under `.shuru/run/`, instead of stopping them.
+    pub detach: bool,
+}
+
+impl Default for RunOptions {
+    fn default() -> Self {
+        RunOptions {
+            jobs: std::thread::available_parallelism().unwrap_or(NonZeroUsize::MIN),
+            force: false,
+            watch: false,
+            output: OutputMode::default(),
+            timestamps: false,
+            parallel: false,
+            keep_going: false,
+            detach: false,
+        }
+    }
+}
+
+/// How long a task gets to exit after SIGTERM before it is killed.
+const TERMINATE_GRACE: Duration = Duration::from_secs(5);
+const WATCH_POLL_INTERVAL: Duration = Duration::from_millis(100);
+/// How often a retry delay checks whether shuru was interrupted.
+const RETRY_POLL_INTERVAL: Duration = Duration::from_millis(50);
+/// Environment variable holding the task's exit code in `on_failure` hooks.
+const EXIT_CODE_ENV: &str = "SHURU_EXIT_CODE";
+
+/// A single step of a task: a command line passed verbatim to the shell, or
+/// an `exec` argv run without any shell.
+#[derive(Debug, PartialEq)]
+enum CommandLine {
+    Shell(String),
+    Exec(Vec<String>),
+}
+
+/// The steps of a task with their placeholders rendered, ready to be spawned
+/// one after another.
+struct PreparedCommand {
+    steps: Vec<CommandLine>,
+    hooks: Hooks,
+    work_dir: PathBuf,
+    env: HashMap<String, String>,
+}
+
+/// Lifecycle hooks of a task, with their command lines rendered like steps.
+struct Hooks {
+    before: Option<Hook>,
+    after: Option<Hook>,
+    on_failure: Option<Hook>,
+    finally: Option<Hook>,
+}
+
+struct Hook {
+    name: &'static str,
+    line: String,
+}
+
+impl Hooks {
+    fn iter(&self) -> impl Iterator<Item = &Hook> {
+        [&self.before, &self.after, &self.on_failure, &self.finally]
+            .into_iter()
+            .flatten()
+    }
+}
+
 pub struct TaskRunner {
     config: Config,
+    options: RunOptions,
+    /// Exit status of every task already run by this runner, so shared
+    /// dependencies run once per invocation.
+    executed: Mutex<HashMap<String, ExitStatus>>,
+    /// Last signal received while tasks were running, forwarded to their
+    /// process groups. Registered when the first task starts.
+    interrupted: OnceLock<InterruptFlag>,
+    /// Tasks with `allow_failure` that failed, with their exit codes.
+    allowed_failures: Mutex<Vec<(String, i32)>>,
+    /// Services started in the background for the current run, in order.
+    services: Mutex<Vec<Service>>,
+    /// Set by `--watch` when files changed during a run: the running step is
+    /// terminated and no further steps, retries or hooks but `finally` start.
+    restart: AtomicBool,
 }
 
 impl TaskRunner {
     pub fn new(config: Config) -> Self {
-        TaskRunner { config }
+        TaskRunner::with_options(config, RunOptions::default())
+    }
+
+    pub fn with_options(config: Config, options: RunOptions) -> Self {
+        TaskRunner {
+            config,
+            options,
+            executed: Mutex::new(HashMap::new()),
+            interrupted: OnceLock::new(),
+            allowed_failures: Mutex::new(Vec::new()),
+            services: Mutex::new(Vec::new()),
+            restart: AtomicBool::new(false),
+        }
     }
 
     fn find_task(&self, name: &str) -> Result<&TaskConfig, Error> {
-        if let Some(task) = self.config.tasks.get(name) {
-            return Ok(task);
+        let task_name = self.resolve_task_name(name)?;
+        Ok(&self.config.tasks[task_name])
+    }
+
+    /// Resolves a task name or alias to the task's name.
+    fn resolve_task_name<'a>(&'a self, name: &str) -> Result<&'a str, Error> {
+        if let Some(task_name) = self.config.resolve_task_name(name) {
+            return Ok(task_name);
         }
 
         let matches = self.search_similar_tasks(name, 0.5);
@@ -37,7 +179,14 @@ impl TaskRunner {
     }
 
     fn search_similar_tasks(&self, name: &str, min_score: f64) -> Vec<String> {
-        let task_keys: Vec<String> = self.config.tasks.keys().cloned().collect();
+        let task_keys: Vec<String> = self
+            .config
+            .tasks
+            .iter()
+            .filter(|(name, task)| !task.is_private(name))
+            .flat_map(|(name, task)| std::iter::once(name).chain(&task.aliases))
+            .cloned()
+            .collect();
 
         shuru_core::utils::fuzzy_match::filter_matches(name, task_keys, min_score)
             .iter()
@@ -45,23 +194,755 @@ impl TaskRunner {
             .collect()
     }
 
-    pub fn run_task(&self, name: &str) -> Result<ExitStatus, Error> {
-        let task = self.find_task(name)?;
-        self.run_dependencies(task)?;
-        let work_dir = self.resolve_work_directory(task)?;
+    /// Resolves a task requested from the command line, which must not be
+    /// private.
+    fn resolve_requested_task<'a>(&'a self, name: &str) -> Result<&'a str, Error> {
+        let task_name = self.resolve_task_name(name)?;
+        if self.config.tasks[task_name].is_private(task_name) {
+            return Err(Error::PrivateTaskError(task_name.to_string()));
+        }
+        Ok(task_name)
+    }
+
+    /// Runs a task and its dependencies. `args` are forwarded to the requested
+    /// task only, never to its dependencies. With `--watch`, the task keeps
+    /// running in watch mode.
+    /// Services started for dependents are stopped once the task finished.
+    pub fn run_task(&self, name: &str, args: &TaskArgs) -> Result<ExitStatus, Error> {
+        let name = self.resolve_requested_task(name)?;
+        let task = &self.config.tasks[name];
+
+        let result = match self.options.watch {
+            true => self.watch_task(name, task, args),
+            false => self.run_with_dependencies(name, args),
+        };
+        self.stop_services();
+        self.report_allowed_failures();
+        self.interrupted_or(result)
+    }
+
+    /// Replaces the result of a run with [`Error::Interrupted`] when shuru
+    /// received a signal, so it exits with the conventional signal code.
+    fn interrupted_or(&self, result: Result<ExitStatus, Error>) -> Result<ExitStatus, Error> {
+        match self.received_signal() {
+            Some(signal) => Err(Error::Interrupted(signal)),
+            None => result,
+        }
+    }
+
+    /// Runs several tasks with their dependencies, in the given order or all
+    /// at once with `parallel`, and prints a summary of how each one went.
+    /// Tasks never run twice, even when several of them share dependencies.
+    pub fn run_tasks(&self, names: &[String], args: &TaskArgs) -> Result<ExitStatus, Error> {
+        let mut unique: Vec<&str> = Vec::new();
+        for name in names {
+            let name = self.resolve_requested_task(name)?;
+            if !unique.contains(&name) {
+                unique.push(name);
+            }
+        }
+
+        if let [name] = unique[..] {
+            return self.run_task(name, args);
+        }
+        if !args.is_empty() {
+            return Err(Error::TaskParamError(
+                "Description: Parameters and extra arguments can only be passed to a single task"
+                    .to_string(),
+            ));
+        }
+        if self.options.watch {
+            return Err(Error::CommandExecutionError(
+                "Description: --watch can only be used with a single task".to_string(),
+            ));
+        }
         let env_path = self.config.build_env_path()?;
-        let shell = Shell::from_env();
-        self.execute_command(task, work_dir, &env_path, &shell)
+        let no_args = TaskArgs::default();
+
+        let outcomes = if self.options.parallel {
+            let graph = TaskGraph::build_all(&self.config, &unique)?;
+            self.check_shells(&graph, &env_path)?;
+            self.scheduler().run_all(&graph, |task_name| {
+                let foreground = unique.contains(&task_name);
+                self.execute_task(task_name, &no_args, &env_path, foreground)
+            })
+        } else {
+            let mut outcomes = Vec::new();
+            let mut failed = false;
+            for name in &unique {
+                let outcome = match failed && !self.options.keep_going {
+                    true => TaskOutcome::NotRun,
+                    false => match self.run_graph(name, &no_args, &env_path, true) {
+                        Ok(status) => TaskOutcome::Succeeded(status),
+                        Err(e) => {
+                            failed = true;
+                            TaskOutcome::Failed(e)
+                        }
+                    },
+                };
+                outcomes.push((name.to_string(), outcome));
+            }
+            outcomes
+        };
+
+        self.stop_services();
+        self.print_summary(&outcomes);
+        self.report_allowed_failures();
+        if let Some(signal) = self.received_signal() {
+            return Err(Error::Interrupted(signal));
+        }
+
+        let mut status = ExitStatus::default();
+        for (_, outcome) in outcomes {
+            match outcome {
+                TaskOutcome::Failed(e) => return Err(e),
+                TaskOutcome::Succeeded(task_status) => status = task_status,
+                TaskOutcome::NotRun => {}
+            }
+        }
+        Ok(status)
+    }
+
+    fn print_summary(&self, outcomes: &[(String, TaskOutcome)]) {
+        let allowed = self
+            .allowed_failures
+            .lock()
+            .unwrap_or_else(|e| e.into_inner());
+
+        let width = outcomes
+            .iter()
+            .map(|(name, _)| name.len())
+            .max()
+            .unwrap_or(0);
+
+        println!();
+        println!("Summary:");
+        for (name, outcome) in outcomes {
+            let result = match outcome {
+                TaskOutcome::Succeeded(_) if allowed.iter().any(|(task, _)| task == name) => {
+                    "\x1b[33mfailed (allowed)\x1b[0m"
+                }
+                TaskOutcome::Succeeded(_) => "\x1b[32mpassed\x1b[0m",
+                TaskOutcome::Failed(_) => "\x1b[31mfailed\x1b[0m",
+                TaskOutcome::NotRun => "\x1b[90mnot run\x1b[0m",
+            };
+            println!("  {:width$}  {}", name, result, width = width);
+        }
+    }
+
+    fn run_with_dependencies(&self, name: &str, args: &TaskArgs) -> Result<ExitStatus, Error> {
+        let env_path = self.config.build_env_path()?;
+        self.run_graph(name, args, &env_path, true)
+    }
+
+    /// Runs `name` after its dependencies. Services among the dependencies
+    /// are started in the background; `name` itself only runs in the
+    /// foreground when `foreground` is set.
+    fn run_graph(
+        &self,
+        name: &str,
+        args: &TaskArgs,
+        env_path: &str,
+        foreground: bool,
+    ) -> Result<ExitStatus, Error> {
+        let graph = TaskGraph::build(&self.config, name)?;
+        let no_args = TaskArgs::default();
+
+        self.check_shells(&graph, env_path)?;
+
+        self.scheduler().run(&graph, |task_name| {
+            let is_root = task_name == name;
+            let task_args = if is_root { args } else { &no_args };
+            self.execute_task(task_name, task_args, env_path, foreground && is_root)
+        })
     }
 
-    fn run_dependencies(&self, task: &TaskConfig) -> Result<(), Error> {
-        for dep in &task.depends {
-            self.run_task(dep)?;
+    fn scheduler(&self) -> Scheduler {
+        Scheduler::new(self.options.jobs).keep_going(self.options.keep_going)
+    }
+
+    /// Fails before anything runs rather than halfway through the graph when
+    /// a configured shell is missing.
+    fn check_shells(&self, graph: &TaskGraph, env_path: &str) -> Result<(), Error> {
+        for task_name in graph.names() {
+            let task = self.find_task(task_name)?;
+            if task.supports_current_platform() {
+                self.shell_for(task, env_path)?;
+            }
         }
         Ok(())
     }
 
-    fn resolve_work_directory(&self, task: &TaskConfig) -> Result<PathBuf, Error> {
+    /// Runs a single task, or starts it in the background when it is a
+    /// service not running in the `foreground`.
+    fn execute_task(
+        &self,
+        name: &str,
+        args: &TaskArgs,
+        env_path: &str,
+        foreground: bool,
+    ) -> Result<ExitStatus, Error> {
+        let task = self.find_task(name)?;
+
+        if let Some(signal) = self.received_signal() {
+            return Err(Error::Interrupted(signal));
+        }
+
+        let status = match self.executed_status(name) {
+            Some(status) if !task.runs_always() => status,
+            _ => {
+                let status = match self.skip_reason(task, env_path)? {
+                    Some(reason) => {
+                        shuru_core::log!("Skipping task '{}': {}.", name, reason);
+                        ExitStatus::default()
+                    }
+                    None => {
+                        let result = match task.is_service() && !foreground {
+                            true => self.launch_service(name, task, args, env_path),
+                            false => self.run_task_command(name, task, args, env_path),
+                        };
+                        let allowed =
+                            task.allow_failure.unwrap_or(false) && self.received_signal().is_none();
+                        // Only the task's own commands may fail; errors in
+                        // shuru itself, like a broken config, still stop it.
+                        match result {
+                            Ok(status) if allowed && !status.success() => {
+                                self.allow_failure(name, shuru_core::utils::exit_code(&status))
+                            }
+                            Err(e) if allowed && is_task_failure(&e) => {
+                                self.allow_failure(name, shuru_core::utils::get_error_code(&e))
+                            }
+                            result => result?,
+                        }
+                    }
+                };
+                self.record_execution(name, status);
+                status
+            }
+        };
+
+        if !status.success() {
+            return Err(Error::TaskFailed {
+                task: name.to_string(),
+                code: shuru_core::utils::exit_code(&status),
+            });
+        }
+
+        Ok(status)
+    }
+
+    /// Evaluates `platforms` and the `if` condition of a task and returns why
+    /// it should be skipped, if it should.
+    fn skip_reason(&self, task: &TaskConfig, env_path: &str) -> Result<Option<String>, Error> {
+        if !task.supports_current_platform() {
+            return Ok(Some(format!("not available on {}", Platform::current())));
+        }
+
+        let Some(condition) = &task.condition else {
+            return Ok(None);
+        };
+
+        if let Some(var) = &condition.env {
+            match (std::env::var(var), &condition.equals) {
+                (Ok(value), Some(expected)) if &value != expected => {
+                    return Ok(Some(format!("${} is not '{}'", var, expected)))
+                }
+                (Ok(value), None) if value.is_empty() => {
+                    return Ok(Some(format!("${} is not set", var)))
+                }
+                (Err(_), _) => return Ok(Some(format!("${} is not set", var))),
+                _ => {}
+            }
+        }
+
+        if let Some(file) = &condition.file {
+            let work_dir = self.resolve_work_directory(task.dir.as_deref())?;
+            if !work_dir.join(file).exists() {
+                return Ok(Some(format!("'{}' does not exist", file)));
+            }
+        }
+
+        if let Some(command) = &condition.command {
+            let work_dir = self.resolve_work_directory(task.dir.as_deref())?;
+            let status = self
+                .shell_for(task, env_path)?
+                .create_command()
+                .arg(command)
+                .current_dir(work_dir)
+                .env("PATH", env_path)
+                .stdin(Stdio::null())
+                .stdout(Stdio::null())
+                .stderr(Stdio::null())
+                .status()
+                .map_err(|e| {
+                    Error::CommandExecutionError(format!(
+                        "Description: Failed to evaluate condition `{}`\n    Technical: {}",
+                        command, e
+                    ))
+                })?;
+            if !status.success() {
+                return Ok(Some(format!("`{}` did not succeed", command)));
+            }
+        }
+
+        Ok(None)
+    }
+
+    fn run_task_command(
+        &self,
+        name: &str,
+        task: &TaskConfig,
+        args: &TaskArgs,
+        env_path: &str,
+    ) -> Result<ExitStatus, Error> {
+        let shell = self.shell_for(task, env_path)?;
+        let prepared = self.prepare_command(name, task, args, &shell)?;
+
+        let fingerprint = match task.sources.is_empty() {
+            true => None,
+            false => Some(Fingerprint::compute(
+                &self.config,
+                task,
+                &prepared
+                    .steps
+                    .iter()
+                    .map(|step| format!("{:?}", step))
+                    .chain(
+                        prepared
+                            .hooks
+                            .iter()
+                            .map(|hook| format!("{}={}", hook.name, hook.line)),
+                    )
+                    .collect::<Vec<_>>(),
+                &prepared.work_dir,
+                &prepared.env,
+                &self.project_dir()?,
+            )?),
+        };
+
+        if let Some(fingerprint) = &fingerprint {
+            if let Some(pattern) = fingerprint.unmatched_sources().first() {
+                shuru_core::log!(
+                    "Warning: `sources` pattern '{}' of task '{}' matches no files; running it every time.",
+                    pattern,
+                    name
+                );
+            }
+            if !self.options.force && fingerprint.is_up_to_date(name, task, &prepared.work_dir) {
+                shuru_core::log!("Task '{}' is up to date.", name);
+                return Ok(ExitStatus::default());
+            }
+        }
+
+        let status = self.execute_with_hooks(name, task, &prepared, env_path, &shell)?;
+
+        if let Some(fingerprint) = fingerprint {
+            if status.success() {
+                fingerprint.save(name)?;
+            }
+        }
+
+        Ok(status)
+    }
+
+    /// Picks the shell for a task: its own `shell`, then the project-wide one,
+    /// then `$SHELL`. Explicitly configured shells must exist on `env_path`.
+    fn shell_for(&self, task: &TaskConfig, env_path: &str) -> Result<Shell, Error> {
+        match task.shell.or(self.config.shell) {
+            Some(name) => {
+                let shell = Shell::from(name);
+                if task.exec.is_none() {
+                    shell.ensure_available(env_path)?;
+                }
+                Ok(shell)
+            }
+            None => Ok(Shell::from_env()),
+        }
+    }
+
+    /// Runs the dependencies of a task once, then runs the task itself and
+    /// restarts it, together with everything it spawned, whenever one of its
+    /// `watch` patterns (or `sources` when there are none) changes.
+    fn watch_task(
+        &self,
+        name: &str,
+        task: &TaskConfig,
+        args: &TaskArgs,
+    ) -> Result<ExitStatus, Error> {
+        let patterns = match (task.watch.is_empty(), task.sources.is_empty()) {
+            (false, _) => &task.watch,
+            (true, false) => &task.sources,
+            (true, true) => {
+                return Err(Error::CommandExecutionError(format!(
+                    "Description: Task '{}' has no files to watch\n    Hint: Add `watch` or `sources` patterns to the task.",
+                    name
+                )))
+            }
+        };
+
+        let env_path = self.config.build_env_path()?;
+        for dep in self.config.dependencies(task) {
+            self.run_graph(dep, &TaskArgs::default(), &env_path, false)?;
+        }
+
+        if let Some(reason) = self.skip_reason(task, &env_path)? {
+            shuru_core::log!("Skipping task '{}': {}.", name, reason);
+            return Ok(ExitStatus::default());
+        }
+        let shell = self.shell_for(task, &env_path)?;
+        let prepared = self.prepare_command(name, task, args, &shell)?;
+        let watcher = FileWatcher::new(&prepared.work_dir, patterns)?;
+        self.interrupt_flag()?;
+
+        shuru_core::log!("Watching {} for changes...", patterns.join(", "));
+
+        // Every run goes through hooks, retries and the timeout like any other
+        // task, on a thread of its own so changes can restart it.
+        loop {
+            self.restart.store(false, Ordering::Relaxed);
+            let path = std::thread::scope(|scope| {
+                let mut run =
+                    Some(scope.spawn(|| {
+                        self.execute_with_hooks(name, task, &prepared, &env_path, &shell)
+                    }));
+
+                loop {
+                    if let Some(signal) = self.received_signal() {
+                        if let Some(run) = run.take() {
+                            let _ = join_run(run);
+                        }
+                        return Err(Error::Interrupted(signal));
+                    }
+
+                    if run.as_ref().is_some_and(|run| run.is_finished()) {
+                        if let Some(run) = run.take() {
+                            match join_run(run) {
+                                Ok(status) if status.success() => {}
+                                Ok(status) => shuru_core::log!(
+                                    "Task '{}' exited with code {}.",
+                                    name,
+                                    shuru_core::utils::exit_code(&status)
+                                ),
+                                Err(e) => shuru_core::log!("{}", e),
+                            }
+                            shuru_core::log!("Waiting for changes...");
+                        }
+                    }
+
+                    if let Some(path) = watcher.wait_for_change(WATCH_POLL_INTERVAL) {
+                        self.restart.store(true, Ordering::Relaxed);
+                        if let Some(run) = run.take() {
+                            let _ = join_run(run);
+                        }
+                        return Ok(path);
+                    }
+                }
+            })?;
+
+            shuru_core::log!(
+                "Change detected in '{}', restarting task '{}'...",
+                path.strip_prefix(&prepared.work_dir)
+                    .unwrap_or(&path)
+                    .display(),
+                name
+            );
+        }
+    }
+
+    /// Starts a service task in the background and waits until its `ready`
+    /// check passes. The service keeps running until the requested task
+    /// finished or, with `detach`, until `shuru --stop`. A service already
+    /// running from `shuru --start` is used as it is.
+    fn launch_service(
+        &self,
+        name: &str,
+        task: &TaskConfig,
+        args: &TaskArgs,
+        env_path: &str,
+    ) -> Result<ExitStatus, Error> {
+        if let ServiceState::Running { pid, .. } = supervisor::state(name) {
+            shuru_core::log!("Service '{}' is already running (pid {}).", name, pid);
+            return Ok(ExitStatus::default());
+        }
+
+        let shell = self.shell_for(task, env_path)?;
+        let prepared = self.prepare_command(name, task, args, &shell)?;
+        let command = self.build_command(&prepared, &prepared.steps[0], env_path, &shell);
+        let timeout = task
+            .ready
+            .as_ref()
+            .map_or(DEFAULT_READY_TIMEOUT, ReadyProbe::timeout);
+        let interrupted = self.interrupt_flag()?;
+
+        shuru_core::log!("Starting service '{}'...", name);
+
+        if self.options.detach {
+            let (mut child, offset) = supervisor::spawn(name, command)?;
+            let readiness = self.readiness(name, task, &prepared, env_path, &shell, |pattern| {
+                Readiness::LogFile {
+                    path: supervisor::log_file(name),
+                    pattern,
+                    offset,
+                }
+            })?;
+            if let Some(mut readiness) = readiness {
+                let ready = service::wait_until_ready(
+                    name,
+                    &mut child,
+                    &mut readiness,
+                    timeout,
+                    &interrupted,
+                    TERMINATE_GRACE,
+                );
+                if let Err(e) = ready {
+                    supervisor::forget(name);
+                    return Err(e);
+                }
+            }
+            shuru_core::log!(
+                "Service '{}' is running in the background (pid {}), logging to {}.",
+                name,
+                child.id(),
+                supervisor::log_file(name).display()
+            );
+            return Ok(ExitStatus::default());
+        }
+
+        let mut output = TaskOutput::new(name, self.options.output, self.options.timestamps);
+        let readiness = self.readiness(name, task, &prepared, env_path, &shell, |pattern| {
+            Readiness::Log(output.watch_for(pattern))
+        })?;
+        let service = Service::start(
+            name,
+            command,
+            output,
+            readiness,
+            timeout,
+            &interrupted,
+            TERMINATE_GRACE,
+        )?;
+        shuru_core::log!("Service '{}' is ready.", name);
+
+        self.services
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .push(service);
+        Ok(ExitStatus::default())
+    }
+
+    /// Builds the check of a service's `ready` table. `log` patterns are
+    /// watched for with `watch_log`, as that depends on where the output goes.
+    fn readiness(
+        &self,
+        name: &str,
+        task: &TaskConfig,
+        prepared: &PreparedCommand,
+        env_path: &str,
+        shell: &Shell,
+        watch_log: impl FnOnce(regex::Regex) -> Readiness,
+    ) -> Result<Option<Readiness>, Error> {
+        let readiness = match &task.ready {
+            Some(ReadyProbe {
+                port: Some(port), ..
+            }) => Readiness::Port(*port),
+            Some(ReadyProbe {
+                http: Some(url), ..
+            }) => Readiness::Http(url.clone()),
+            Some(ReadyProbe {
+                log: Some(pattern), ..
+            }) => {
+                let pattern = regex::Regex::new(pattern).map_err(|e| {
+                    ConfigValidationError::InvalidServiceError {
+                        task: name.to_string(),
+                        reason: e.to_string(),
+                    }
+                })?;
+                watch_log(pattern)
+            }
+            Some(ReadyProbe {
+                command: Some(line),
+                ..
+            }) => Readiness::Command(self.build_command(
+                prepared,
+                &CommandLine::Shell(line.clone()),
+                env_path,
+                shell,
+            )),
+            _ => return Ok(None),
+        };
+        Ok(Some(readiness))
+    }
+
+    /// Starts a service task in the background, after its dependencies, and
+    /// leaves it running once it is ready. Use with [`RunOptions::detach`] so
+    /// the services it depends on keep running as well.
+    pub fn start_service(&self, name: &str, args: &TaskArgs) -> Result<ExitStatus, Error> {
+        let name = self.resolve_requested_task(name)?;
+        if !self.config.tasks[name].is_service() {
+            return Err(Error::NotAService(name.to_string()));
+        }
+
+        let env_path = self.config.build_env_path()?;
+        let result = self.run_graph(name, args, &env_path, false);
+        self.stop_services();
+        self.report_allowed_failures();
+        self.interrupted_or(result)
+    }
+
+    /// Stops a service started with [`TaskRunner::start_service`].
+    pub fn stop_service(&self, name: &str) -> Result<ExitStatus, Error> {
+        let name = self.resolve_service(name)?;
+
+        match supervisor::state(name) {
+            ServiceState::Running { pid, .. } => {
+                shuru_core::log!("Stopping service '{}' (pid {})...", name, pid);
+                supervisor::stop(name, pid, TERMINATE_GRACE).map_err(|e| {
+                    Error::CommandExecutionError(format!(
+                        "Description: Failed to stop service '{}'\n    Technical: {}",
+                        name, e
+                    ))
+                })?;
+                shuru_core::log!("Service '{}' stopped.", name);
+            }
+            ServiceState::Stale { pid } => shuru_core::log!(
+                "Service '{}' is not running; removed its stale pid {}.",
+                name,
+                pid
+            ),
+            ServiceState::Stopped => shuru_core::log!("Service '{}' is not running.", name),
+        }
+        Ok(ExitStatus::default())
+    }
+
+    /// Prints whether each service task of the project is running.
+    pub fn print_service_status(&self) -> Result<ExitStatus, Error> {
+        let mut names: Vec<&str> = self
+            .config
+            .tasks
+            .iter()
+            .filter(|(_, task)| task.is_service())
+            .map(|(name, _)| name.as_str())
+            .collect();
+        names.sort();
+
+        if names.is_empty() {
+            println!("No service tasks defined.");
+            return Ok(ExitStatus::default());
+        }
+
+        let width = names.iter().map(|name| name.len()).max().unwrap_or(0);
+        for name in names {
+            let state = match supervisor::state(name) {
+                ServiceState::Running { pid, uptime } => format!(
+                    "\x1b[32mrunning\x1b[0m  pid {}, up {}",
+                    pid,
+                    supervisor::format_uptime(uptime)
+                ),
+                ServiceState::Stale { pid } => {
+                    format!("\x1b[33mstopped\x1b[0m  stale pid {} removed", pid)
+                }
+                ServiceState::Stopped => "\x1b[90mstopped\x1b[0m".to_string(),
+            };
+            println!("  {:width$}  {}", name, state, width = width);
+        }
+        Ok(ExitStatus::default())
+    }
+
+    /// Prints the log of a service started with [`TaskRunner::start_service`],
+    /// and with `follow` everything it logs afterwards.
+    pub fn print_service_logs(&self, name: &str, follow: bool) -> Result<ExitStatus, Error> {
+        let name = self.resolve_service(name)?;
+
+        supervisor::print_logs(name, follow).map_err(|e| match e.kind() {
+            std::io::ErrorKind::NotFound => Error::CommandExecutionError(format!(
+                "Description: No logs found for service '{}'\n    Hint: Start it with `shuru --start {}`.",
+                name, name
+            )),
+            _ => Error::CommandExecutionError(format!(
+                "Description: Failed to read the logs of service '{}'\n    Technical: {}",
+                name, e
+            )),
+        })?;
+        Ok(ExitStatus::default())
+    }
+
+    fn resolve_service<'a>(&'a self, name: &str) -> Result<&'a str, Error> {
+        let name = self.resolve_task_name(name)?;
+        if !self.config.tasks[name].is_service() {
+            return Err(Error::NotAService(name.to_string()));
+        }
+        Ok(name)
+    }
+
+    /// Stops the services started for the current run, most recent first.
+    fn stop_services(&self) {
+        let services =
+            std::mem::take(&mut *self.services.lock().unwrap_or_else(|e| e.into_inner()));
+        for service in services.into_iter().rev() {
+            shuru_core::log!("Stopping service '{}'...", service.name());
+            let name = service.name().to_string();
+            if let Err(e) = service.stop(TERMINATE_GRACE) {
+                shuru_core::log!("Failed to stop service '{}': {}", name, e);
+            }
+        }
+    }
+
+    /// Records the failure of a task with `allow_failure` and lets its
+    /// dependents carry on as if it succeeded.
+    fn allow_failure(&self, name: &str, code: i32) -> ExitStatus {
+        shuru_core::log!(
+            "Task '{}' failed with exit code {}; continuing because it allows failure.",
+            name,
+            code
+        );
+        self.allowed_failures
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .push((name.to_string(), code));
+        ExitStatus::default()
+    }
+
+    fn report_allowed_failures(&self) {
+        let allowed = self
+            .allowed_failures
+            .lock()
+            .unwrap_or_else(|e| e.into_inner());
+        if allowed.is_empty() {
+            return;
+        }
+
+        println!();
+        println!("\x1b[33mWarnings:\x1b[0m");
+        for (name, code) in allowed.iter() {
+            println!(
+                "  \x1b[33m{} failed with exit code {} (allow_failure)\x1b[0m",
+                name, code
+            );
+        }
+    }
+
+    fn executed_status(&self, name: &str) -> Option<ExitStatus> {
+        self.executed
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .get(name)
+            .copied()
+    }
+
+    fn record_execution(&self, name: &str, status: ExitStatus) {
+        self.executed
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .insert(name.to_string(), status);
+    }
+
+    /// Directory of the shuru.toml, which shuru reads from the current
+    /// directory. Project `env_file`s and `.shuru/` are relative to it.
+    fn project_dir(&self) -> Result<PathBuf, Error> {
+        self.resolve_work_directory(None)
+    }
+
+    fn resolve_work_directory(&self, dir: Option<&str>) -> Result<PathBuf, Error> {
         let current_dir = std::env::current_dir().map_err(|e| {
             Error::CommandExecutionError(format!(
                 "Description: Failed to get current directory: {}",
@@ -69,7 +950,7 @@ impl TaskRunner {
             ))
         })?;
 
-        if let Some(dir) = &task.dir {
+        if let Some(dir) = dir {
             let resolved_dir = current_dir.join(dir);
             self.validate_directory(&resolved_dir, &current_dir)?;
             Ok(resolved_dir)
@@ -108,52 +989,593 @@ impl TaskRunner {
         Ok(())
     }
 
-    fn execute_command(
+    fn prepare_command(
         &self,
+        name: &str,
         task: &TaskConfig,
-        work_dir: PathBuf,
+        args: &TaskArgs,
+        shell: &Shell,
+    ) -> Result<PreparedCommand, Error> {
+        let params = args.resolve_params(name, task)?;
+        let dir = task.dir.as_ref().map(|dir| render_template(dir, &params));
+        let work_dir = self.resolve_work_directory(dir.as_deref())?;
+
+        let values = Self::shell_values(&params, &args.extra, shell);
+        let hook = |name: &'static str, line: &Option<String>| {
+            line.as_ref().map(|line| Hook {
+                name,
+                line: render_template(line, &values),
+            })
+        };
+
+        Ok(PreparedCommand {
+            steps: Self::render_steps(task, &params, &args.extra, shell),
+            hooks: Hooks {
+                before: hook("before", &task.before),
+                after: hook("after", &task.after),
+                on_failure: hook("on_failure", &task.on_failure),
+                finally: hook("finally", &task.finally),
+            },
+            env: self.resolve_env(task, &params, &work_dir)?,
+            work_dir,
+        })
+    }
+
+    /// Builds the variables set on top of the process environment, in
+    /// increasing precedence: project `env_file`s, the project `[env]`, task
+    /// `env_file`s, then the task's inline `env`. Inline values can reference
+    /// `${VAR}` from any of the layers below or the process environment.
+    fn resolve_env(
+        &self,
+        task: &TaskConfig,
+        params: &HashMap<String, String>,
+        work_dir: &Path,
+    ) -> Result<HashMap<String, String>, Error> {
+        let mut env = self.config.project_env(&self.project_dir()?)?;
+
+        for file in &task.env_file {
+            load_env_file(&work_dir.join(file), &mut env)?;
+        }
+
+        let rendered = task
+            .env
+            .iter()
+            .map(|(key, value)| (key.clone(), render_template(value, params)))
+            .collect();
+        let inline = interpolate_all(&rendered, &env);
+        env.extend(inline);
+
+        Ok(env)
+    }
+
+    fn build_command(
+        &self,
+        prepared: &PreparedCommand,
+        step: &CommandLine,
         env_path: &str,
         shell: &Shell,
-    ) -> Result<ExitStatus, Error> {
-        let mut command = shell.create_command();
-
-        let task_command_with_args = if let Some((command, args)) = task.command.split_once(' ') {
-            let escaped_args: Vec<std::ffi::OsString> = args
-                .split_whitespace()
-                .map(String::from)
-                .map(|arg| shell.escape_argument(&arg))
-                .collect();
-
-            format!(
-                "{} {}",
-                command,
-                escaped_args
-                    .iter()
-                    .map(|arg| arg.to_string_lossy())
-                    .collect::<Vec<_>>()
-                    .join(" ")
-            )
-        } else {
-            task.command.clone()
+    ) -> std::process::Command {
+        let mut command = match step {
+            CommandLine::Shell(line) => {
+                let mut command = shell.create_command();
+                command.arg(line);
+                command
+            }
+            CommandLine::Exec(argv) => {
+                let mut command = std::process::Command::new(&argv[0]);
+                command.args(&argv[1..]);
+                command
+            }
         };
 
         command
-            .current_dir(work_dir)
+            .current_dir(&prepared.work_dir)
             .env("PATH", env_path)
-            .envs(&task.env)
-            .arg(&task_command_with_args);
+            .envs(&prepared.env);
 
-        command.status().map_err(|e| {
+        command
+    }
+
+    /// Runs a task up to `retries + 1` times until it succeeds, waiting
+    /// `retry_delay` (doubled each time with exponential backoff) in between.
+    fn execute_with_retries(
+        &self,
+        name: &str,
+        task: &TaskConfig,
+        prepared: &PreparedCommand,
+        env_path: &str,
+        shell: &Shell,
+    ) -> Result<ExitStatus, Error> {
+        let attempts = task.retries.unwrap_or(0).saturating_add(1);
+        let mut delay = task.retry_delay();
+        let mut attempt = 1;
+
+        loop {
+            if attempts > 1 {
+                shuru_core::log!("Running task '{}' (attempt {}/{})", name, attempt, attempts);
+            }
+
+            let result = self.execute_command(name, task, prepared, env_path, shell);
+            let failed = match &result {
+                Ok(status) => !status.success(),
+                Err(Error::TaskStepFailed { .. } | Error::TaskTimedOut { .. }) => true,
+                Err(_) => false,
+            };
+
+            if !failed
+                || attempt == attempts
+                || self.received_signal().is_some()
+                || self.restart_requested()
+            {
+                if attempts > 1 {
+                    match failed {
+                        true => {
+                            shuru_core::log!("Task '{}' failed after {} attempt(s).", name, attempt)
+                        }
+                        false => shuru_core::log!(
+                            "Task '{}' succeeded after {} attempt(s).",
+                            name,
+                            attempt
+                        ),
+                    }
+                }
+                return result;
+            }
+
+            match &result {
+                Err(e) => shuru_core::log!("{}", e),
+                Ok(status) => shuru_core::log!(
+                    "Task '{}' exited with code {}.",
+                    name,
+                    shuru_core::utils::exit_code(status)
+                ),
+            }
+            match delay.is_zero() {
+                true => shuru_core::log!("Retrying task '{}'...", name),
+                false => shuru_core::log!("Retrying task '{}' in {:?}...", name, delay),
+            }
+            if !self.sleep_before_retry(delay) {
+                shuru_core::log!("Not retrying task '{}' after an interruption.", name);
+                return result;
+            }
+
+            if task.retry_backoff.unwrap_or_default() == RetryBackoff::Exponential {
+                delay = delay.saturating_mul(2);
+            }
+            attempt += 1;
+        }
+    }
+
+    /// Waits `delay` before the next attempt of a task, returning `false`
+    /// as soon as shuru receives a signal or a watch restart is requested.
+    fn sleep_before_retry(&self, delay: Duration) -> bool {
+        let start = Instant::now();
+        loop {
+            if self.received_signal().is_some() || self.restart_requested() {
+                return false;
+            }
+            let remaining = delay.saturating_sub(start.elapsed());
+            if remaining.is_zero() {
+                return true;
+            }
+            std::thread::sleep(remaining.min(RETRY_POLL_INTERVAL));
+        }
+    }
+
+    /// Runs the steps of a task in order and stops at the first failing one.
+    /// With a `timeout`, the whole process group of the running step is
+    /// terminated once the task as a whole exceeds it.
+    fn execute_command(
+        &self,
+        name: &str,
+        task: &TaskConfig,
+        prepared: &PreparedCommand,
+        env_path: &str,
+        shell: &Shell,
+    ) -> Result<ExitStatus, Error> {
+        let timeout = task.timeout();
+        // A timeout too large to represent never expires.
+        let deadline = timeout.and_then(|timeout| Instant::now().checked_add(timeout));
+        let output = TaskOutput::new(name, self.options.output, self.options.timestamps);
+        let interrupted = self.interrupt_flag()?;
+        let mut status = ExitStatus::default();
+
+        for (index, step) in prepared.steps.iter().enumerate() {
+            let command = self.build_command(prepared, step, env_path, shell);
+
+            let cancelled = Some(&self.restart);
+            status =
+                match self.run_step(command, &output, deadline, Some(&interrupted), cancelled)? {
+                    Some(status) => status,
+                    None => {
+                        return Err(Error::TaskTimedOut {
+                            task: name.to_string(),
+                            timeout: task.timeout.clone().unwrap_or_default(),
+                        })
+                    }
+                };
+
+            if !status.success() {
+                if prepared.steps.len() > 1 {
+                    return Err(Error::TaskStepFailed {
+                        task: name.to_string(),
+                        step: index + 1,
+                        steps: prepared.steps.len(),
+                        code: shuru_core::utils::exit_code(&status),
+                    });
+                }
+                break;
+            }
+        }
+
+        Ok(status)
+    }
+
+    /// Runs a single command to completion in its own process group. Signals
+    /// recorded in `interrupted` are forwarded to the group, which is
+    /// terminated once `cancelled` is set, and `None` is returned when the
+    /// deadline passed.
+    fn run_step(
+        &self,
+        mut command: std::process::Command,
+        output: &TaskOutput,
+        deadline: Option<Instant>,
+        interrupted: Option<&AtomicUsize>,
+        cancelled: Option<&AtomicBool>,
+    ) -> Result<Option<ExitStatus>, Error> {
+        output.configure(&mut command);
+        // Only one task at a time can hold the terminal; the others, like
+        // tasks running in parallel, are kept off it.
+        let mut foreground = process::Foreground::claim();
+        if !foreground.is_held() && process::stdin_is_terminal() {
+            process::keep_off_terminal(&mut command);
+        }
+
+        let mut child = process::spawn_in_group(&mut command).map_err(|e| {
             Error::CommandExecutionError(format!("Description: Failed to execute command: {}", e))
-        })
+        })?;
+        let readers = output.capture(&mut child);
+        foreground.hand_to(Some(child.id()));
+
+        let mut stopped = None;
+        let status = match process::wait_for(&mut child, deadline, interrupted, cancelled)? {
+            Wait::Exited(status) => Some(status),
+            Wait::Interrupted(signal) => Some(process::forward_signal(
+                &mut child,
+                signal,
+                TERMINATE_GRACE,
+            )?),
+            Wait::Cancelled => Some(process::terminate_group(&mut child, TERMINATE_GRACE)?),
+            Wait::Stopped(signal) => {
+                stopped = Some(signal);
+                Some(process::terminate_group(&mut child, TERMINATE_GRACE)?)
+            }
+            Wait::TimedOut => {
+                process::terminate_group(&mut child, TERMINATE_GRACE)?;
+                None
+            }
+        };
+        // Ctrl-C only reached the task holding the terminal; act as if shuru
+        // was interrupted itself.
+        if let (true, Some(flag), Some(signal)) = (
+            foreground.is_held(),
+            interrupted,
+            status.as_ref().and_then(process::terminal_signal),
+        ) {
+            flag.store(signal as usize, Ordering::Relaxed);
+            process::terminate_detached_group(child.id(), TERMINATE_GRACE)?;
+        }
+        drop(foreground);
+        TaskOutput::join(readers);
+
+        if let Some(signal) = stopped {
+            return Err(Error::CommandExecutionError(format!(
+                "Description: Command was stopped by signal {} for using the terminal while another task held it\n    Hint: Run tasks that use the terminal on their own or with `--jobs 1`.",
+                signal
+            )));
+        }
+        Ok(status)
+    }
+
+    /// Runs a task's `before` hook, its command and its `after` hook, then
+    /// `on_failure` if any of them failed and `finally` in every case. A
+    /// `finally` hook also runs when shuru is interrupted.
+    fn execute_with_hooks(
+        &self,
+        name: &str,
+        task: &TaskConfig,
+        prepared: &PreparedCommand,
+        env_path: &str,
+        shell: &Shell,
+    ) -> Result<ExitStatus, Error> {
+        let mut result = match &prepared.hooks.before {
+            Some(hook) => self.run_hook(name, hook, prepared, env_path, shell, None),
+            None => Ok(ExitStatus::default()),
+        }
+        .and_then(|_| self.execute_with_retries(name, task, prepared, env_path, shell))
+        .and_then(|status| match &prepared.hooks.after {
+            Some(hook) if status.success() && !self.restart_requested() => {
+                self.run_hook(name, hook, prepared, env_path, shell, None)
+            }
+            _ => Ok(status),
+        });
+
+        let exit_code = match &result {
+            Ok(status) if status.success() => None,
+            Ok(status) => Some(shuru_core::utils::exit_code(status)),
+            Err(e) => Some(shuru_core::utils::get_error_code(e)),
+        };
+        // A run stopped by `--watch` did not fail; it is only restarted.
+        let exit_code = exit_code.filter(|_| !self.restart_requested());
+        if let (Some(code), Some(hook)) = (exit_code, &prepared.hooks.on_failure) {
+            if let Err(e) = self.run_hook(name, hook, prepared, env_path, shell, Some(code)) {
+                shuru_core::log!("{}", e);
+            }
+        }
+
+        if let Some(hook) = &prepared.hooks.finally {
+            let finally = self.run_hook(name, hook, prepared, env_path, shell, None);
+            match (&result, finally) {
+                (Ok(status), Err(e)) if status.success() => result = Err(e),
+                (_, Err(e)) => shuru_core::log!("{}", e),
+                _ => {}
+            }
+        }
+
+        result
+    }
+
+    /// Runs a hook command line of a task with the task's directory and
+    /// environment. `on_failure` hooks get the task's exit code in
+    /// `SHURU_EXIT_CODE`.
+    fn run_hook(
+        &self,
+        name: &str,
+        hook: &Hook,
+        prepared: &PreparedCommand,
+        env_path: &str,
+        shell: &Shell,
+        exit_code: Option<i32>,
+    ) -> Result<ExitStatus, Error> {
+        shuru_core::log!("Running `{}` hook of task '{}'...", hook.name, name);
+
+        let step = CommandLine::Shell(hook.line.clone());
+        let mut command = self.build_command(prepared, &step, env_path, shell);
+        if let Some(code) = exit_code {
+            command.env(EXIT_CODE_ENV, code.to_string());
+        }
+
+        // Cleanup hooks run to completion even after an interrupt or restart.
+        let (interrupted, cancelled) = match hook.name {
+            "on_failure" | "finally" => (None, None),
+            _ => (Some(self.interrupt_flag()?), Some(&self.restart)),
+        };
+        let output = TaskOutput::new(name, self.options.output, self.options.timestamps);
+        let status = self
+            .run_step(command, &output, None, interrupted.as_deref(), cancelled)?
+            .unwrap_or_default();
+
+        if !status.success() {
+            return Err(Error::TaskHookFailed {
+                task: name.to_string(),
+                hook: hook.name.to_string(),
+                code: shuru_core::utils::exit_code(&status),
+            });
+        }
+        Ok(status)
+    }
+
+    fn restart_requested(&self) -> bool {
+        self.restart.load(Ordering::Relaxed)
+    }
+
+    fn received_signal(&self) -> Option<i32> {
+        self.interrupted
+            .get()
+            .map(|interrupted| interrupted.flag().load(Ordering::Relaxed) as i32)
+            .filter(|&signal| signal != 0)
+    }
+
+    /// Registers, once per runner, the flag recording SIGINT, SIGTERM and
+    /// SIGHUP. The signals act as usual again once the runner is dropped.
+    fn interrupt_flag(&self) -> Result<Arc<AtomicUsize>, Error> {
+        if let Some(interrupted) = self.interrupted.get() {
+            return Ok(Arc::clone(interrupted.flag()));
+        }
+        let flag = InterruptFlag::register()?;
+        Ok(Arc::clone(self.interrupted.get_or_init(|| flag).flag()))
+    }
+
+    /// Builds the steps of a task. Shell command lines are kept verbatim,
+    /// with `{{name}}` placeholders filled with escaped parameter values and
+    /// the escaped extra arguments placed at `{{args}}`, or at the end of the
+    /// last step when no step has the placeholder. `exec` tasks get the raw
+    /// values, with `{{args}}` expanding to one argument per extra argument.
+    fn render_steps(
+        task: &TaskConfig,
+        params: &HashMap<String, String>,
+        extra_args: &[String],
+        shell: &Shell,
+    ) -> Vec<CommandLine> {
+        if let Some(exec) = &task.exec {
+            let has_args_placeholder = exec.iter().any(|arg| arg == ARGS_PLACEHOLDER);
+            let mut argv = Vec::with_capacity(exec.len() + extra_args.len());
+
+            for arg in exec {
+                match arg.as_str() {
+                    ARGS_PLACEHOLDER => argv.extend(extra_args.iter().cloned()),
+                    _ => argv.push(render_template(arg, params)),
+                }
+            }
+            if !has_args_placeholder {
+                argv.extend(extra_args.iter().cloned());
+            }
+
+            return vec![CommandLine::Exec(argv)];
+        }
+
+        let values = Self::shell_values(params, extra_args, shell);
+        let escaped_args = &values[ARGS_PARAM];
+
+        let steps = task.steps();
+        let has_args_placeholder = steps.iter().any(|step| step.contains(ARGS_PLACEHOLDER));
+        let last = steps.len().saturating_sub(1);
+
+        steps
+            .iter()
+            .enumerate()
+            .map(|(index, step)| {
+                let rendered = render_template(step, &values);
+                if index == last && !has_args_placeholder && !escaped_args.is_empty() {
+                    CommandLine::Shell(format!("{} {}", rendered, escaped_args))
+                } else {
+                    CommandLine::Shell(rendered)
+                }
+            })
+            .collect()
     }
 
-    pub fn run_default(&self) -> Result<ExitStatus, Error> {
+    /// Shell-escaped parameter values, plus the escaped extra arguments under
+    /// `args`, for rendering command lines run by `shell`.
+    fn shell_values(
+        params: &HashMap<String, String>,
+        extra_args: &[String],
+        shell: &Shell,
+    ) -> HashMap<String, String> {
+        let escape = |value: &str| shell.escape_argument(value).to_string_lossy().into_owned();
+
+        let mut values: HashMap<String, String> = params
+            .iter()
+            .map(|(name, value)| (name.clone(), escape(value)))
+            .collect();
+        values.insert(
+            ARGS_PARAM.to_string(),
+            extra_args
+                .iter()
+                .map(|arg| escape(arg))
+                .collect::<Vec<_>>()
+                .join(" "),
+        );
+        values
+    }
+
+    pub fn run_default(&self, args: &TaskArgs) -> Result<ExitStatus, Error> {
         self.config
             .tasks
             .iter()
             .find(|(_, task_config)| task_config.default.unwrap_or(false))
-            .map(|(task_name, _)| self.run_task(task_name))
+            .map(|(task_name, _)| self.run_task(task_name, args))
             .unwrap_or(Err(Error::DefaultCommandNotFound))
     }
 }
+
+/// Whether `error` means a command of the task itself failed, which
+/// `allow_failure` tolerates: a non-zero exit, a timeout or a service that
+/// exited or never became ready.
+fn is_task_failure(error: &Error) -> bool {
+    matches!(
+        error,
+        Error::TaskFailed { .. }
+            | Error::TaskStepFailed { .. }
+            | Error::TaskHookFailed { .. }
+            | Error::TaskTimedOut { .. }
+            | Error::ServiceExited { .. }
+            | Error::ServiceNotReady { .. }
+    )
+}
+
+/// Waits for a `--watch` run to finish, passing on a panic of its thread.
+fn join_run(
+    run: std::thread::ScopedJoinHandle<'_, Result<ExitStatus, Error>>,
+) -> Result<ExitStatus, Error> {
+    run.join()
+        .unwrap_or_else(|panic| std::panic::resume_unwind(panic))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn task(toml: &str) -> TaskConfig {
+        toml::from_str(toml).unwrap()
+    }
+
+    fn render(task: &TaskConfig, params: &[(&str, &str)], args: &[&str]) -> Vec<CommandLine> {
+        let params = params
+            .iter()
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+            .collect();
+        let args: Vec<String> = args.iter().map(|arg| arg.to_string()).collect();
+        TaskRunner::render_steps(task, &params, &args, &Shell::Unknown)
+    }
+
+    fn shell(line: &str) -> CommandLine {
+        CommandLine::Shell(line.to_string())
+    }
+
+    fn quote(argument: &str) -> String {
+        Shell::Unknown
+            .escape_argument(argument)
+            .to_string_lossy()
+            .into_owned()
+    }
+
+    fn exec(argv: &[&str]) -> CommandLine {
+        CommandLine::Exec(argv.iter().map(|arg| arg.to_string()).collect())
+    }
+
+    #[test]
+    fn passes_shell_syntax_through_verbatim() {
+        let task = task(r#"command = "echo \"a b\" | grep a && ls *.rs > out 2>&1""#);
+
+        assert_eq!(
+            render(&task, &[], &[]),
+            [shell(r#"echo "a b" | grep a && ls *.rs > out 2>&1"#)]
+        );
+    }
+
+    #[test]
+    fn escapes_params_and_appends_args_to_the_last_step() {
+        let task = task(r#"commands = ["cd {{dir}}", "cargo test"]"#);
+
+        assert_eq!(
+            render(&task, &[("dir", "my dir")], &["--", "it's"]),
+            [
+                shell(&format!("cd {}", quote("my dir"))),
+                shell(&format!("cargo test -- {}", quote("it's"))),
+            ]
+        );
+    }
+
+    #[test]
+    fn expands_args_placeholder_in_place() {
+        let task = task(r#"command = "run {{args}} | tee log""#);
+
+        assert_eq!(
+            render(&task, &[], &["a b", "c"]),
+            [shell(&format!("run {} c | tee log", quote("a b")))]
+        );
+        assert_eq!(render(&task, &[], &[]), [shell("run  | tee log")]);
+    }
+
+    #[test]
+    fn renders_exec_argv_without_quoting() {
+        let task = task(r#"exec = ["grep", "-r", "{{pattern}}"]"#);
+
+        assert_eq!(
+            render(&task, &[("pattern", "a b|c")], &["src", "it's"]),
+            [exec(&["grep", "-r", "a b|c", "src", "it's"])]
+        );
+    }
+
+    #[test]
+    fn expands_exec_args_placeholder_into_separate_arguments() {
+        let task = task(r#"exec = ["docker", "run", "{{args}}", "image"]"#);
+
+        assert_eq!(
+            render(&task, &[], &["-e", "A=b c"]),
+            [exec(&["docker", "run", "-e", "A=b c", "image"])]
+        );
+        assert_eq!(render(&task, &[], &[]), [exec(&["docker", "run", "image"])]);
+    }
+}
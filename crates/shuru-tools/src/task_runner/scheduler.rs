@@ -0,0 +1,396 @@
+use std::collections::{HashMap, VecDeque};
+use std::num::NonZeroUsize;
+use std::panic::{self, AssertUnwindSafe};
+use std::process::ExitStatus;
+use std::sync::mpsc;
+
+use shuru_core::{
+    config::Config,
+    error::{ConfigValidationError, Error},
+};
+
+/// Dependency graph of the requested tasks and everything they transitively
+/// depend on.
+///
+/// Nodes are stored in post-order, so every task comes after its
+/// dependencies and the last requested task is always the last node.
+pub struct TaskGraph {
+    names: Vec<String>,
+    dependencies: Vec<Vec<usize>>,
+    roots: Vec<usize>,
+}
+
+impl TaskGraph {
+    pub fn build(config: &Config, root: &str) -> Result<Self, Error> {
+        Self::build_all(config, &[root])
+    }
+
+    pub fn build_all<S: AsRef<str>>(config: &Config, roots: &[S]) -> Result<Self, Error> {
+        let mut graph = TaskGraph {
+            names: Vec::new(),
+            dependencies: Vec::new(),
+            roots: Vec::new(),
+        };
+        let mut indices = HashMap::new();
+        let mut visiting = Vec::new();
+
+        for root in roots {
+            let index = graph.visit(config, root.as_ref(), &mut indices, &mut visiting)?;
+            graph.roots.push(index);
+        }
+
+        Ok(graph)
+    }
+
+    fn visit(
+        &mut self,
+        config: &Config,
+        name: &str,
+        indices: &mut HashMap<String, usize>,
+        visiting: &mut Vec<String>,
+    ) -> Result<usize, Error> {
+        if let Some(position) = visiting.iter().position(|visited| visited == name) {
+            let mut cycle = visiting[position..].to_vec();
+            cycle.push(name.to_string());
+            return Err(ConfigValidationError::DependencyCycleError(cycle.join(" -> ")).into());
+        }
+
+        let task = config
+            .tasks
+            .get(name)
+            .ok_or_else(|| Error::CommandNotFound(name.to_string()))?;
+
+        // Tasks with `run = "always"` get a node of their own for every dependent.
+        if !task.runs_always() {
+            if let Some(&index) = indices.get(name) {
+                return Ok(index);
+            }
+        }
+
+        visiting.push(name.to_string());
+        let dependencies = config
+            .dependencies(task)
+            .map(|dep| self.visit(config, dep, indices, visiting))
+            .collect::<Result<Vec<_>, _>>()?;
+        visiting.pop();
+
+        let index = self.names.len();
+        self.names.push(name.to_string());
+        self.dependencies.push(dependencies);
+        if !task.runs_always() {
+            indices.insert(name.to_string(), index);
+        }
+
+        Ok(index)
+    }
+
+    /// Names of all tasks in the graph, dependencies first.
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.names.iter().map(String::as_str)
+    }
+
+    fn len(&self) -> usize {
+        self.names.len()
+    }
+
+    fn root(&self) -> usize {
+        self.len() - 1
+    }
+}
+
+/// Result of a node, `None` when it was never started.
+type NodeResult = Option<Result<ExitStatus, Error>>;
+
+/// Result of a requested task once the scheduler is done.
+pub enum TaskOutcome {
+    Succeeded(ExitStatus),
+    Failed(Error),
+    /// Never started because an earlier task failed.
+    NotRun,
+}
+
+/// Runs the tasks of a [`TaskGraph`] with up to `jobs` of them at once.
+///
+/// A task becomes ready once all of its dependencies succeeded. As soon as a
+/// task fails no further tasks are started, unless `keep_going` is set, in
+/// which case only the tasks depending on the failed one are left out. Tasks
+/// already running are always waited for.
+pub struct Scheduler {
+    jobs: NonZeroUsize,
+    keep_going: bool,
+}
+
+impl Scheduler {
+    pub fn new(jobs: NonZeroUsize) -> Self {
+        Scheduler {
+            jobs,
+            keep_going: false,
+        }
+    }
+
+    pub fn keep_going(mut self, keep_going: bool) -> Self {
+        self.keep_going = keep_going;
+        self
+    }
+
+    /// Runs the graph and returns the status of its last requested task, or
+    /// the first failure.
+    pub fn run<F>(&self, graph: &TaskGraph, execute: F) -> Result<ExitStatus, Error>
+    where
+        F: Fn(&str) -> Result<ExitStatus, Error> + Sync,
+    {
+        let (mut results, first_failure) = self.schedule(graph, execute);
+
+        if let Some(index) = first_failure {
+            return results[index].take().unwrap_or(Ok(ExitStatus::default()));
+        }
+
+        results[graph.root()].take().unwrap_or_else(|| {
+            Err(Error::CommandExecutionError(format!(
+                "Description: Task '{}' could not be scheduled",
+                graph.names[graph.root()]
+            )))
+        })
+    }
+
+    /// Runs the graph and returns the outcome of every requested task, in
+    /// the order they were requested.
+    pub fn run_all<F>(&self, graph: &TaskGraph, execute: F) -> Vec<(String, TaskOutcome)>
+    where
+        F: Fn(&str) -> Result<ExitStatus, Error> + Sync,
+    {
+        let (mut results, _) = self.schedule(graph, execute);
+
+        graph
+            .roots
+            .iter()
+            .map(|&index| {
+                let outcome = match results[index].take() {
+                    Some(Ok(status)) => TaskOutcome::Succeeded(status),
+                    Some(Err(e)) => TaskOutcome::Failed(e),
+                    None => TaskOutcome::NotRun,
+                };
+                (graph.names[index].clone(), outcome)
+            })
+            .collect()
+    }
+
+    /// Executes the tasks of `graph` and returns the result of every node
+    /// that was started, along with the index of the first one that failed.
+    fn schedule<F>(&self, graph: &TaskGraph, execute: F) -> (Vec<NodeResult>, Option<usize>)
+    where
+        F: Fn(&str) -> Result<ExitStatus, Error> + Sync,
+    {
+        let mut remaining: Vec<usize> = graph.dependencies.iter().map(Vec::len).collect();
+        let mut dependents = vec![Vec::new(); graph.len()];
+        for (index, dependencies) in graph.dependencies.iter().enumerate() {
+            for &dep in dependencies {
+                dependents[dep].push(index);
+            }
+        }
+
+        let mut ready: VecDeque<usize> = (0..graph.len())
+            .filter(|&index| remaining[index] == 0)
+            .collect();
+        let mut started = vec![false; graph.len()];
+        let mut results: Vec<NodeResult> = (0..graph.len()).map(|_| None).collect();
+        let mut first_failure = None;
+
+        std::thread::scope(|scope| {
+            let (sender, receiver) = mpsc::channel();
+            let mut running = 0;
+
+            loop {
+                while (self.keep_going || first_failure.is_none()) && running < self.jobs.get() {
+                    let Some(index) = ready.pop_front() else {
+                        break;
+                    };
+                    let sender = sender.clone();
+                    let execute = &execute;
+                    let name = graph.names[index].as_str();
+                    started[index] = true;
+                    running += 1;
+                    scope.spawn(move || {
+                        // A panicking task must still report back, or the
+                        // scheduler would wait for it forever.
+                        let result = panic::catch_unwind(AssertUnwindSafe(|| execute(name)))
+                            .unwrap_or_else(|_| {
+                                Err(Error::CommandExecutionError(format!(
+                                    "Description: Task '{}' panicked while running\n    Hint: This is a bug in shuru, please report it.",
+                                    name
+                                )))
+                            });
+                        let _ = sender.send((index, result));
+                    });
+                }
+
+                if running == 0 {
+                    break;
+                }
+
+                let Ok((index, result)) = receiver.recv() else {
+                    break;
+                };
+                running -= 1;
+
+                match &result {
+                    Ok(status) if status.success() => {
+                        for &dependent in &dependents[index] {
+                            remaining[dependent] -= 1;
+                            if remaining[dependent] == 0 {
+                                ready.push_back(dependent);
+                            }
+                        }
+                    }
+                    _ => {
+                        first_failure.get_or_insert(index);
+                    }
+                }
+                results[index] = Some(result);
+            }
+        });
+
+        if first_failure.is_some() {
+            let cancelled: Vec<&str> = (0..graph.len())
+                .filter(|&index| !started[index])
+                .map(|index| graph.names[index].as_str())
+                .collect();
+            if !cancelled.is_empty() {
+                shuru_core::log!("Cancelled pending tasks: {}", cancelled.join(", "));
+            }
+        }
+
+        (results, first_failure)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex;
+
+    use super::*;
+
+    fn config(toml: &str) -> Config {
+        toml::from_str(toml).unwrap()
+    }
+
+    fn jobs(jobs: usize) -> NonZeroUsize {
+        NonZeroUsize::new(jobs).unwrap()
+    }
+
+    /// Runs `names` with the tasks in `failing` failing, and returns the
+    /// outcomes together with the order the tasks were started in.
+    fn run_all(
+        config: &Config,
+        names: &[&str],
+        failing: &[&str],
+        keep_going: bool,
+    ) -> (Vec<(String, TaskOutcome)>, Vec<String>) {
+        let graph = TaskGraph::build_all(config, names).unwrap();
+        let started = Mutex::new(Vec::new());
+        let outcomes = Scheduler::new(jobs(1))
+            .keep_going(keep_going)
+            .run_all(&graph, |name| {
+                started.lock().unwrap().push(name.to_string());
+                match failing.contains(&name) {
+                    true => Err(Error::TaskFailed {
+                        task: name.to_string(),
+                        code: 1,
+                    }),
+                    false => Ok(ExitStatus::default()),
+                }
+            });
+        (outcomes, started.into_inner().unwrap())
+    }
+
+    const PIPELINE: &str = r#"
+        [tasks.build]
+        command = "true"
+        [tasks.lint]
+        command = "true"
+        [tasks.test]
+        command = "true"
+        depends = ["build"]
+        [tasks.deploy]
+        command = "true"
+        depends = ["test", "build"]
+    "#;
+
+    #[test]
+    fn runs_dependencies_first_and_once() {
+        let config = config(PIPELINE);
+        let (outcomes, started) = run_all(&config, &["deploy", "test"], &[], false);
+
+        assert_eq!(started, ["build", "test", "deploy"]);
+        assert!(outcomes
+            .iter()
+            .all(|(_, outcome)| matches!(outcome, TaskOutcome::Succeeded(_))));
+    }
+
+    #[test]
+    fn stops_starting_tasks_after_a_failure() {
+        let config = config(PIPELINE);
+        let (outcomes, started) = run_all(&config, &["test", "lint"], &["build"], false);
+
+        assert_eq!(started, ["build"]);
+        assert!(matches!(outcomes[0].1, TaskOutcome::NotRun));
+        assert!(matches!(outcomes[1].1, TaskOutcome::NotRun));
+    }
+
+    #[test]
+    fn keep_going_only_skips_dependents_of_a_failure() {
+        let config = config(PIPELINE);
+        let (outcomes, started) = run_all(&config, &["deploy", "lint"], &["build"], true);
+
+        assert_eq!(started, ["build", "lint"]);
+        assert!(matches!(outcomes[0].1, TaskOutcome::NotRun));
+        assert!(matches!(outcomes[1].1, TaskOutcome::Succeeded(_)));
+    }
+
+    #[test]
+    fn run_returns_the_first_failure() {
+        let config = config(PIPELINE);
+        let graph = TaskGraph::build(&config, "deploy").unwrap();
+        let result = Scheduler::new(jobs(4)).run(&graph, |name| match name {
+            "test" => Err(Error::TaskFailed {
+                task: name.to_string(),
+                code: 3,
+            }),
+            _ => Ok(ExitStatus::default()),
+        });
+
+        assert!(matches!(result, Err(Error::TaskFailed { task, code: 3 }) if task == "test"));
+    }
+
+    #[test]
+    fn reports_a_panicking_task_as_failed() {
+        let config = config(PIPELINE);
+        let graph = TaskGraph::build_all(&config, &["test", "lint"]).unwrap();
+        let outcomes =
+            Scheduler::new(jobs(2))
+                .keep_going(true)
+                .run_all(&graph, |name| match name {
+                    "build" => panic!("task thread panicked"),
+                    _ => Ok(ExitStatus::default()),
+                });
+
+        assert!(matches!(outcomes[0].1, TaskOutcome::NotRun));
+        assert!(matches!(outcomes[1].1, TaskOutcome::Succeeded(_)));
+    }
+
+    #[test]
+    fn detects_dependency_cycles() {
+        let config = config(
+            r#"
+            [tasks.a]
+            command = "true"
+            depends = ["b"]
+            [tasks.b]
+            command = "true"
+            depends = ["a"]
+            "#,
+        );
+        let error = TaskGraph::build(&config, "a").err().unwrap().to_string();
+        assert!(error.contains("a -> b -> a"), "{}", error);
+    }
+}
@@ -0,0 +1,210 @@
+use std::io::{self, BufRead, BufReader, Write};
+use std::net::{Ipv4Addr, Ipv6Addr, SocketAddr, TcpStream, ToSocketAddrs};
+use std::path::PathBuf;
+use std::process::{Child, Command, ExitStatus, Stdio};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc::Receiver;
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+
+use regex::Regex;
+use shuru_core::error::Error;
+
+use shuru_tools::task_runner::{output::TaskOutput, process};
+
+const READY_POLL_INTERVAL: Duration = Duration::from_millis(250);
+const PROBE_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// How a service tells that it is ready, built from its `ready` table.
+pub enum Readiness {
+    /// A connection to the local port succeeds over IPv4 or IPv6.
+    Port(u16),
+    /// A GET request to the `http://` URL answers with a 2xx status.
+    Http(String),
+    /// A line matching the `log` pattern was printed.
+    Log(Receiver<()>),
+    /// A line matching the pattern was written to the log file after `offset`.
+    LogFile {
+        path: PathBuf,
+        pattern: Regex,
+        offset: u64,
+    },
+    /// The command exits successfully.
+    Command(Command),
+}
+
+impl Readiness {
+    fn check(&mut self) -> bool {
+        match self {
+            // Services may listen on either loopback address for `localhost`.
+            Readiness::Port(port) => [
+                SocketAddr::from((Ipv4Addr::LOCALHOST, *port)),
+                SocketAddr::from((Ipv6Addr::LOCALHOST, *port)),
+            ]
+            .iter()
+            .any(|address| TcpStream::connect_timeout(address, PROBE_TIMEOUT).is_ok()),
+            Readiness::Http(url) => http_ok(url).unwrap_or(false),
+            Readiness::Log(matched) => matched.try_recv().is_ok(),
+            Readiness::LogFile {
+                path,
+                pattern,
+                offset,
+            } => std::fs::read(path).is_ok_and(|contents| {
+                let start = (*offset as usize).min(contents.len());
+                String::from_utf8_lossy(&contents[start..])
+                    .lines()
+                    .any(|line| pattern.is_match(line))
+            }),
+            Readiness::Command(command) => command
+                .stdin(Stdio::null())
+                .stdout(Stdio::null())
+                .stderr(Stdio::null())
+                .status()
+                .is_ok_and(|status| status.success()),
+        }
+    }
+}
+
+/// A task with `service = true` running in the background, in its own
+/// process group.
+pub struct Service {
+    name: String,
+    child: Child,
+    // Kept alive so grouped output is printed when the service is dropped.
+    _output: TaskOutput,
+    readers: Vec<JoinHandle<()>>,
+}
+
+impl Service {
+    /// Spawns the service and waits until `readiness` passes, or right away
+    /// without a check. The service is stopped again when it does not become
+    /// ready within `timeout` or shuru receives a signal in the meantime.
+    pub fn start(
+        name: &str,
+        mut command: Command,
+        output: TaskOutput,
+        readiness: Option<Readiness>,
+        timeout: Duration,
+        interrupted: &AtomicUsize,
+        grace: Duration,
+    ) -> Result<Service, Error> {
+        output.configure(&mut command);
+        // Services run next to other tasks and never hold the terminal.
+        if process::stdin_is_terminal() {
+            process::keep_off_terminal(&mut command);
+        }
+        let mut child = process::spawn_in_group(&mut command).map_err(|e| {
+            Error::CommandExecutionError(format!(
+                "Description: Failed to start service '{}': {}",
+                name, e
+            ))
+        })?;
+        let readers = output.capture(&mut child);
+        let mut service = Service {
+            name: name.to_string(),
+            child,
+            _output: output,
+            readers,
+        };
+
+        if let Some(mut readiness) = readiness {
+            let ready = wait_until_ready(
+                name,
+                &mut service.child,
+                &mut readiness,
+                timeout,
+                interrupted,
+                grace,
+            );
+            if let Err(e) = ready {
+                TaskOutput::join(std::mem::take(&mut service.readers));
+                return Err(e);
+            }
+        }
+        Ok(service)
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Terminates the service together with everything it spawned. Output
+    /// of processes that left its group is no longer waited for.
+    pub fn stop(mut self, grace: Duration) -> io::Result<ExitStatus> {
+        let status = process::terminate_group(&mut self.child, grace)?;
+        TaskOutput::join(std::mem::take(&mut self.readers));
+        Ok(status)
+    }
+}
+
+/// Waits until `readiness` passes for the service running as `child`. When
+/// the service exits first, does not become ready within `timeout` or shuru
+/// receives a signal, an error is returned and its process group is gone.
+pub fn wait_until_ready(
+    name: &str,
+    child: &mut Child,
+    readiness: &mut Readiness,
+    timeout: Duration,
+    interrupted: &AtomicUsize,
+    grace: Duration,
+) -> Result<(), Error> {
+    // A timeout too large to represent never expires.
+    let deadline = Instant::now().checked_add(timeout);
+    loop {
+        if let Some(status) = child.try_wait()? {
+            return Err(Error::ServiceExited {
+                task: name.to_string(),
+                code: shuru_core::utils::exit_code(&status),
+            });
+        }
+        let signal = interrupted.load(Ordering::Relaxed) as i32;
+        if signal != 0 {
+            process::forward_signal(child, signal, grace)?;
+            return Err(Error::Interrupted(signal));
+        }
+        if readiness.check() {
+            return Ok(());
+        }
+        if deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+            process::terminate_group(child, grace)?;
+            return Err(Error::ServiceNotReady {
+                task: name.to_string(),
+                timeout: format!("{:?}", timeout),
+            });
+        }
+        std::thread::sleep(READY_POLL_INTERVAL);
+    }
+}
+
+/// Sends a plain HTTP/1.0 GET request to `url` and checks for a 2xx status.
+fn http_ok(url: &str) -> io::Result<bool> {
+    let rest = url.strip_prefix("http://").unwrap_or(url);
+    let (host, path) = match rest.find('/') {
+        Some(index) => rest.split_at(index),
+        None => (rest, "/"),
+    };
+    let address = match host.contains(':') {
+        true => host.to_string(),
+        false => format!("{}:80", host),
+    };
+    let socket = address
+        .to_socket_addrs()?
+        .next()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, address.clone()))?;
+
+    let mut stream = TcpStream::connect_timeout(&socket, PROBE_TIMEOUT)?;
+    stream.set_read_timeout(Some(PROBE_TIMEOUT))?;
+    stream.set_write_timeout(Some(PROBE_TIMEOUT))?;
+    write!(
+        stream,
+        "GET {} HTTP/1.0\r\nHost: {}\r\nConnection: close\r\n\r\n",
+        path, host
+    )?;
+
+    let mut status_line = String::new();
+    BufReader::new(stream).read_line(&mut status_line)?;
+    Ok(status_line
+        .split_whitespace()
+        .nth(1)
+        .is_some_and(|code| code.starts_with('2') && code.len() == 3))
+}
@@ -1,25 +1,40 @@
 use std::{
     ffi::OsString,
+    path::Path,
     process::{Command, Stdio},
 };
 
 use shell_quote::Quote;
+use shuru_core::{error::Error, task_config::ShellName};
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Shell {
     Bash,
     Fish,
     Zsh,
+    Dash,
+    Pwsh,
+    Nu,
     Unknown,
 }
 
 impl Shell {
-    pub fn create_command(&self) -> Command {
-        let (shell_cmd, shell_args): (&str, &[&str]) = match self {
+    fn invocation(&self) -> (&'static str, &'static [&'static str]) {
+        match self {
             Shell::Bash => ("bash", &["-c"]),
             Shell::Fish => ("fish", &["-c"]),
             Shell::Zsh => ("zsh", &["-c"]),
+            Shell::Dash => ("dash", &["-c"]),
+            Shell::Pwsh => ("pwsh", &["-NoProfile", "-NonInteractive", "-Command"]),
+            Shell::Nu => ("nu", &["-c"]),
             Shell::Unknown => ("/bin/sh", &["-cu"]),
-        };
+        }
+    }
+
+    /// Creates a command running in a process group of its own, so signals
+    /// can be forwarded to it and everything it spawns.
+    pub fn create_command(&self) -> Command {
+        let (shell_cmd, shell_args) = self.invocation();
 
         let mut command = Command::new(shell_cmd);
         command
@@ -27,16 +42,18 @@ impl Shell {
             .stdout(Stdio::inherit())
             .stderr(Stdio::inherit())
             .stdin(Stdio::inherit());
+        #[cfg(unix)]
+        {
+            use std::os::unix::process::CommandExt;
+            command.process_group(0);
+        }
         command
     }
 
+    /// Async counterpart of [`Shell::create_command`]; wait for the child
+    /// with `process::wait_forwarding_signals`.
     pub fn create_async_command(&self) -> tokio::process::Command {
-        let (shell_cmd, shell_args): (&str, &[&str]) = match self {
-            Shell::Bash => ("bash", &["-c"]),
-            Shell::Fish => ("fish", &["-c"]),
-            Shell::Zsh => ("zsh", &["-c"]),
-            Shell::Unknown => ("/bin/sh", &["-cu"]),
-        };
+        let (shell_cmd, shell_args) = self.invocation();
 
         let mut command = tokio::process::Command::new(shell_cmd);
         command
@@ -44,6 +61,8 @@ impl Shell {
             .stdout(Stdio::inherit())
             .stderr(Stdio::inherit())
             .stdin(Stdio::inherit());
+        #[cfg(unix)]
+        command.process_group(0);
         command
     }
 
@@ -52,10 +71,48 @@ impl Shell {
             Shell::Bash => shell_quote::Bash::quote(argument),
             Shell::Fish => shell_quote::Fish::quote(argument),
             Shell::Zsh => shell_quote::Zsh::quote(argument),
-            Shell::Unknown => shell_quote::Sh::quote(argument),
+            Shell::Dash | Shell::Unknown => shell_quote::Sh::quote(argument),
+            // Single-quoted PowerShell strings are literal; quotes are doubled,
+            // including the typographic ones PowerShell also accepts.
+            Shell::Pwsh => {
+                let mut quoted = String::with_capacity(argument.len() + 2);
+                quoted.push('\'');
+                for c in argument.chars() {
+                    if matches!(c, '\'' | '\u{2018}' | '\u{2019}' | '\u{201A}' | '\u{201B}') {
+                        quoted.push(c);
+                    }
+                    quoted.push(c);
+                }
+                quoted.push('\'');
+                quoted.into()
+            }
+            // Single-quoted Nushell strings are literal but cannot contain a
+            // single quote, so those fall back to an escaped double-quoted string.
+            Shell::Nu if !argument.contains('\'') => format!("'{}'", argument).into(),
+            Shell::Nu => format!(
+                "\"{}\"",
+                argument.replace('\\', "\\\\").replace('"', "\\\"")
+            )
+            .into(),
         }
     }
 
+    /// Checks that the shell binary can be found on `env_path`.
+    pub fn ensure_available(&self, env_path: &str) -> Result<(), Error> {
+        let (program, _) = self.invocation();
+
+        let found = if Path::new(program).is_absolute() {
+            Path::new(program).is_file()
+        } else {
+            std::env::split_paths(env_path).any(|dir| dir.join(program).is_file())
+        };
+
+        if !found {
+            return Err(Error::ShellNotFound(program.to_string()));
+        }
+        Ok(())
+    }
+
     pub fn from_env() -> Self {
         match std::env::var("SHELL") {
             Ok(shell) if shell.contains("bash") => Shell::Bash,
@@ -65,3 +122,84 @@ impl Shell {
         }
     }
 }
+
+impl From<ShellName> for Shell {
+    fn from(name: ShellName) -> Self {
+        match name {
+            ShellName::Sh => Shell::Unknown,
+            ShellName::Bash => Shell::Bash,
+            ShellName::Zsh => Shell::Zsh,
+            ShellName::Fish => Shell::Fish,
+            ShellName::Dash => Shell::Dash,
+            ShellName::Pwsh => Shell::Pwsh,
+            ShellName::Nu => Shell::Nu,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TRICKY: &[&str] = &[
+        "",
+        "plain",
+        "two words",
+        "it's",
+        "\"quoted\"",
+        "$HOME `id` $(id)",
+        "a|b && c; d > e",
+        "*.rs",
+        "back\\slash",
+        "line\nbreak",
+    ];
+
+    /// Echoes `argument` through `shell` and returns what it printed.
+    fn round_trip(shell: Shell, argument: &str) -> String {
+        let line = format!(
+            "printf %s {}",
+            shell.escape_argument(argument).to_string_lossy()
+        );
+        let output = shell
+            .create_command()
+            .arg(line)
+            .stdout(Stdio::piped())
+            .output()
+            .unwrap();
+        assert!(
+            output.status.success(),
+            "{:?} failed on {:?}",
+            shell,
+            argument
+        );
+        String::from_utf8(output.stdout).unwrap()
+    }
+
+    #[test]
+    fn escaped_arguments_survive_the_shell() {
+        for shell in [Shell::Unknown, Shell::Bash] {
+            for &argument in TRICKY {
+                assert_eq!(round_trip(shell, argument), argument, "{:?}", shell);
+            }
+        }
+    }
+
+    #[test]
+    fn pwsh_doubles_every_single_quote_character() {
+        assert_eq!(Shell::Pwsh.escape_argument("a b"), "'a b'");
+        assert_eq!(Shell::Pwsh.escape_argument("it's"), "'it''s'");
+        assert_eq!(
+            Shell::Pwsh.escape_argument("\u{2018}x\u{2019} \u{201A}y\u{201B}"),
+            "'\u{2018}\u{2018}x\u{2019}\u{2019} \u{201A}\u{201A}y\u{201B}\u{201B}'"
+        );
+    }
+
+    #[test]
+    fn nu_falls_back_to_double_quotes_for_single_quotes() {
+        assert_eq!(Shell::Nu.escape_argument("a b"), "'a b'");
+        assert_eq!(
+            Shell::Nu.escape_argument(r#"it's "x" \"#),
+            r#""it's \"x\" \\""#
+        );
+    }
+}
@@ -0,0 +1,191 @@
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, Stdio};
+use std::time::{Duration, SystemTime};
+
+use shuru_core::error::Error;
+
+use shuru_tools::task_runner::process;
+
+/// Project-local directory holding the pid and log files of services started
+/// with `shuru --start`.
+const RUN_DIR: &str = ".shuru/run";
+const FOLLOW_POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+/// State of a supervised service according to its pid file.
+pub enum ServiceState {
+    Running {
+        pid: u32,
+        uptime: Duration,
+    },
+    Stopped,
+    /// The pid file named a process group that no longer exists, or whose
+    /// pid now belongs to another process, and was removed.
+    Stale {
+        pid: u32,
+    },
+}
+
+pub fn log_file(name: &str) -> PathBuf {
+    Path::new(RUN_DIR).join(format!("{}.log", name))
+}
+
+fn pid_file(name: &str) -> PathBuf {
+    Path::new(RUN_DIR).join(format!("{}.pid", name))
+}
+
+/// Reads the pid and the start time of the leader from the pid file of a
+/// service.
+fn read_pid_file(name: &str) -> Option<(u32, String)> {
+    let contents = fs::read_to_string(pid_file(name)).ok()?;
+    let (pid, started) = contents.trim().split_once('\n')?;
+    Some((pid.parse().ok()?, started.to_string()))
+}
+
+/// Reads the pid file of a service, removing it when its process group is
+/// gone or the pid was reused by another process.
+pub fn state(name: &str) -> ServiceState {
+    let path = pid_file(name);
+    if !path.exists() {
+        return ServiceState::Stopped;
+    }
+
+    match read_pid_file(name) {
+        Some((pid, started)) if process::detached_group_running(pid, &started) => {
+            let uptime = fs::metadata(&path)
+                .and_then(|metadata| metadata.modified())
+                .ok()
+                .and_then(|started| SystemTime::now().duration_since(started).ok())
+                .unwrap_or_default();
+            ServiceState::Running { pid, uptime }
+        }
+        Some((pid, _)) => {
+            forget(name);
+            ServiceState::Stale { pid }
+        }
+        None => {
+            forget(name);
+            ServiceState::Stopped
+        }
+    }
+}
+
+/// Spawns a service detached from the terminal in a new session, with its
+/// output appended to its log file, and records its pid together with its
+/// start time. Returns the child with the size of the log before it started.
+pub fn spawn(name: &str, command: Command) -> Result<(Child, u64), Error> {
+    let supervisor_error = |e: io::Error| {
+        Error::CommandExecutionError(format!(
+            "Description: Failed to start service '{}' in the background\n    Technical: {}",
+            name, e
+        ))
+    };
+
+    fs::create_dir_all(RUN_DIR).map_err(supervisor_error)?;
+    let log = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(log_file(name))
+        .map_err(supervisor_error)?;
+    let offset = log.metadata().map_err(supervisor_error)?.len();
+
+    let mut command = without_process_group(&command);
+    command
+        .stdin(Stdio::null())
+        .stdout(log.try_clone().map_err(supervisor_error)?)
+        .stderr(log);
+    #[cfg(unix)]
+    {
+        use std::os::unix::process::CommandExt;
+        // SAFETY: setsid is async-signal-safe. The new session also makes
+        // the service the leader of a new process group.
+        unsafe {
+            command.pre_exec(|| match libc::setsid() {
+                -1 => Err(io::Error::last_os_error()),
+                _ => Ok(()),
+            });
+        }
+    }
+    let child = command.spawn().map_err(supervisor_error)?;
+
+    let started = process::start_time(child.id()).unwrap_or_default();
+    fs::write(pid_file(name), format!("{}\n{}\n", child.id(), started))
+        .map_err(supervisor_error)?;
+    Ok((child, offset))
+}
+
+/// Copies `command` without the process group task commands are created
+/// with: `setsid` fails for a process that already leads a group.
+fn without_process_group(command: &Command) -> Command {
+    let mut copy = Command::new(command.get_program());
+    copy.args(command.get_args());
+    if let Some(dir) = command.get_current_dir() {
+        copy.current_dir(dir);
+    }
+    for (key, value) in command.get_envs() {
+        match value {
+            Some(value) => copy.env(key, value),
+            None => copy.env_remove(key),
+        };
+    }
+    copy
+}
+
+/// Removes the pid file of a service that failed to start.
+pub fn forget(name: &str) {
+    let _ = fs::remove_file(pid_file(name));
+}
+
+/// Stops a running service together with everything it spawned. Nothing is
+/// signalled when the recorded group is gone or its pid was reused meanwhile.
+pub fn stop(name: &str, pid: u32, grace: Duration) -> io::Result<()> {
+    if let Some((recorded, started)) = read_pid_file(name) {
+        if recorded == pid && process::detached_group_running(pid, &started) {
+            process::terminate_detached_group(pid, grace)?;
+        }
+    }
+    forget(name);
+    Ok(())
+}
+
+/// Prints the log of a service. With `follow`, keeps printing what is
+/// appended to it until shuru is interrupted.
+pub fn print_logs(name: &str, follow: bool) -> io::Result<()> {
+    let mut log = File::open(log_file(name))?;
+    let mut stdout = io::stdout();
+    io::copy(&mut log, &mut stdout)?;
+    stdout.flush()?;
+
+    if !follow {
+        return Ok(());
+    }
+
+    let mut position = log.stream_position()?;
+    let mut buffer = Vec::new();
+    loop {
+        std::thread::sleep(FOLLOW_POLL_INTERVAL);
+
+        let length = fs::metadata(log_file(name))?.len();
+        if length < position {
+            // The log was truncated or replaced; start over.
+            log = File::open(log_file(name))?;
+            position = 0;
+        }
+        log.seek(SeekFrom::Start(position))?;
+        buffer.clear();
+        position += log.read_to_end(&mut buffer)? as u64;
+        stdout.write_all(&buffer)?;
+        stdout.flush()?;
+    }
+}
+
+/// Formats how long a service has been running, e.g. `2h 5m` or `42s`.
+pub fn format_uptime(uptime: Duration) -> String {
+    let seconds = uptime.as_secs();
+    match seconds {
+        0..=59 => format!("{}s", seconds),
+        60..=3599 => format!("{}m {}s", seconds / 60, seconds % 60),
+        _ => format!("{}h {}m", seconds / 3600, seconds / 60 % 60),
+    }
+}
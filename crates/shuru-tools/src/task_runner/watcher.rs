@@ -0,0 +1,120 @@
+use std::path::{Component, Path, PathBuf};
+use std::sync::mpsc;
+use std::time::Duration;
+
+use notify::{EventKind, RecursiveMode, Watcher};
+use shuru_core::error::Error;
+
+use shuru_tools::task_runner::fingerprint::resolve_glob;
+
+/// Changes arriving within this window are treated as a single change.
+const DEBOUNCE: Duration = Duration::from_millis(200);
+
+/// Watches the files matching a task's glob patterns.
+pub struct FileWatcher {
+    _watcher: notify::RecommendedWatcher,
+    events: mpsc::Receiver<notify::Result<notify::Event>>,
+    patterns: Vec<glob::Pattern>,
+}
+
+impl FileWatcher {
+    pub fn new(work_dir: &Path, patterns: &[String]) -> Result<Self, Error> {
+        let (sender, events) = mpsc::channel();
+        let mut watcher = notify::recommended_watcher(sender).map_err(Self::watch_error)?;
+
+        let mut roots: Vec<PathBuf> = patterns
+            .iter()
+            .map(|pattern| Self::watch_root(&work_dir.join(pattern)))
+            .collect();
+        roots.sort();
+        roots.dedup_by(|root, parent| root.starts_with(parent));
+
+        for root in roots {
+            watcher
+                .watch(&root, RecursiveMode::Recursive)
+                .map_err(Self::watch_error)?;
+        }
+
+        let patterns = patterns
+            .iter()
+            .map(|pattern| {
+                let full_pattern = resolve_glob(work_dir, pattern);
+                glob::Pattern::new(&full_pattern.to_string_lossy()).map_err(|e| {
+                    Error::CommandExecutionError(format!(
+                        "Description: Invalid glob pattern '{}': {}",
+                        pattern, e
+                    ))
+                })
+            })
+            .collect::<Result<_, _>>()?;
+
+        Ok(FileWatcher {
+            _watcher: watcher,
+            events,
+            patterns,
+        })
+    }
+
+    /// Waits up to `timeout` for a watched file to change and returns its
+    /// path once no further changes arrive for the debounce window.
+    pub fn wait_for_change(&self, timeout: Duration) -> Option<PathBuf> {
+        let changed = self.next_change(timeout)?;
+        while self.next_change(DEBOUNCE).is_some() {}
+        Some(changed)
+    }
+
+    fn next_change(&self, timeout: Duration) -> Option<PathBuf> {
+        let deadline = std::time::Instant::now() + timeout;
+
+        loop {
+            let remaining = deadline.saturating_duration_since(std::time::Instant::now());
+            let event = self.events.recv_timeout(remaining).ok()?.ok();
+
+            let changed = event
+                .filter(|event| {
+                    matches!(
+                        event.kind,
+                        EventKind::Create(_) | EventKind::Modify(_) | EventKind::Remove(_)
+                    )
+                })
+                .and_then(|event| {
+                    event
+                        .paths
+                        .into_iter()
+                        .find(|path| self.patterns.iter().any(|p| p.matches_path(path)))
+                });
+
+            if changed.is_some() {
+                return changed;
+            }
+        }
+    }
+
+    /// The deepest existing directory of a pattern that contains no glob
+    /// characters, e.g. `src` for `src/**/*.ts`.
+    fn watch_root(pattern: &Path) -> PathBuf {
+        let mut root = PathBuf::new();
+        for component in pattern.components() {
+            if let Component::Normal(part) = component {
+                if part.to_string_lossy().contains(['*', '?', '[', '{']) {
+                    break;
+                }
+            }
+            root.push(component);
+        }
+
+        while !root.exists() {
+            if !root.pop() {
+                break;
+            }
+        }
+        root
+    }
+
+    fn watch_error(error: notify::Error) -> Error {
+        Error::CommandExecutionError(format!(
+            "Description: Failed to watch files for changes: {}",
+            error
+        ))
+    }
+}
@@ -2,8 +2,11 @@
 async fn main() {
     dotenvy::dotenv().ok();
 
-    if let Err(e) = shuru_cli::run().await {
-        eprintln!("\x1b[31mError:\x1b[0m {}", e);
-        std::process::exit(shuru_core::utils::get_error_code(e));
+    match shuru_cli::run().await {
+        Ok(status) => std::process::exit(shuru_core::utils::exit_code(&status)),
+        Err(e) => {
+            eprintln!("\x1b[31mError:\x1b[0m {}", e);
+            std::process::exit(shuru_core::utils::get_error_code(&e));
+        }
     }
 }